@@ -0,0 +1,147 @@
+use crate::status::Status;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Last known state of a subject
+pub struct Entry {
+    pub status: Status,
+    pub checked_at: i64,
+    pub failures: u32,
+}
+
+impl Entry {
+    /// Time elapsed since the subject was checked
+    pub fn age(&self) -> Duration {
+        let age = Utc::now().timestamp().saturating_sub(self.checked_at);
+        Duration::from_secs(age.max(0) as u64)
+    }
+}
+
+/// On-disk cache of the last status of each subject, with the number of
+/// consecutive checks that didn't find it ACTIVE
+pub struct Cache {
+    connection: Mutex<Connection>,
+    active_ttl: Duration,
+    inactive_ttl: Duration,
+}
+
+impl Cache {
+    /// Open or create the cache, ACTIVE results stay fresh for `active_ttl` and
+    /// every other status for `inactive_ttl`
+    pub fn open(path: &str, active_ttl: Duration, inactive_ttl: Duration) -> Result<Self, String> {
+        let connection =
+            Connection::open(path).map_err(|e| format!("Cache Open Failed: {}: {}", path, e))?;
+
+        // WAL keeps the frequent small writes cheap
+        connection
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 PRAGMA synchronous = NORMAL;
+                 CREATE TABLE IF NOT EXISTS results (
+                     subject TEXT PRIMARY KEY,
+                     status TEXT NOT NULL,
+                     checked_at INTEGER NOT NULL,
+                     failures INTEGER NOT NULL DEFAULT 0
+                 );",
+            )
+            .map_err(|e| format!("Cache Setup Failed: {}", e))?;
+
+        Ok(Cache {
+            connection: Mutex::new(connection),
+            active_ttl,
+            inactive_ttl,
+        })
+    }
+
+    /// Last known state of a subject, whatever its age, read on the blocking
+    /// pool so the workers never wait on the database
+    pub async fn entry(self: &Arc<Self>, subject: &str) -> Result<Option<Entry>, String> {
+        let cache = self.clone();
+        let subject = subject.to_string();
+        tokio::task::spawn_blocking(move || cache.read_entry(&subject))
+            .await
+            .map_err(|e| format!("Cache Read Failed: {}", e))?
+    }
+
+    fn read_entry(&self, subject: &str) -> Result<Option<Entry>, String> {
+        let row: Option<(String, i64, u32)> = self
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT status, checked_at, failures FROM results WHERE subject = ?1",
+                params![subject],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Cache Read Failed: {}", e))?;
+
+        Ok(row.and_then(|(status, checked_at, failures)| {
+            Some(Entry {
+                status: status.parse().ok()?,
+                checked_at,
+                failures,
+            })
+        }))
+    }
+
+    /// Last status of a subject, `None` when unknown or expired
+    pub async fn get(self: &Arc<Self>, subject: &str) -> Result<Option<Status>, String> {
+        let Some(entry) = self.entry(subject).await? else {
+            return Ok(None);
+        };
+
+        let ttl = if entry.status == Status::Active {
+            self.active_ttl
+        } else {
+            self.inactive_ttl
+        };
+
+        Ok((entry.age() < ttl).then_some(entry.status))
+    }
+
+    /// Subjects whose last status is the given one
+    pub fn subjects_with_status(&self, status: Status) -> Result<Vec<String>, String> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT subject FROM results WHERE status = ?1 ORDER BY subject")
+            .map_err(|e| format!("Cache Read Failed: {}", e))?;
+
+        let subjects = statement
+            .query_map(params![status.as_str()], |row| row.get(0))
+            .map_err(|e| format!("Cache Read Failed: {}", e))?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| format!("Cache Read Failed: {}", e))?;
+        Ok(subjects)
+    }
+
+    /// Store the statuses subjects were just found with, counting consecutive
+    /// failures, in a single transaction
+    pub fn put_all(&self, results: &[(String, Status)]) -> Result<(), String> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection
+            .transaction()
+            .map_err(|e| format!("Cache Write Failed: {}", e))?;
+        {
+            let mut statement = transaction
+                .prepare_cached(
+                    "INSERT INTO results (subject, status, checked_at, failures) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (subject) DO UPDATE SET status = ?2, checked_at = ?3,
+                         failures = CASE WHEN ?4 THEN failures + 1 ELSE 0 END",
+                )
+                .map_err(|e| format!("Cache Write Failed: {}", e))?;
+            let now = Utc::now().timestamp();
+            for (subject, status) in results {
+                let failed = *status != Status::Active;
+                statement
+                    .execute(params![subject, status.as_str(), now, failed])
+                    .map_err(|e| format!("Cache Write Failed: {}", e))?;
+            }
+        }
+        transaction
+            .commit()
+            .map_err(|e| format!("Cache Write Failed: {}", e))
+    }
+}
@@ -0,0 +1,21 @@
+use std::collections::HashSet;
+
+/// Default checkpoint file of a run
+pub fn default_path(output_file: &str) -> String {
+    format!("{}.checkpoint", output_file)
+}
+
+/// Load the subjects completed by a previous run, one per line
+pub fn load_completed(path: &str) -> Result<HashSet<String>, String> {
+    if !std::path::Path::new(path).exists() {
+        return Ok(HashSet::new());
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Checkpoint Read Failed: {}: {}", path, e))?;
+    Ok(contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+// HTTP Error Codes that indicate the site exists
+const ACTIVE_CODES: [u16; 28] = [
+    200, 201, 202, 203, 204, 205, 206, // Successful responses
+    300, 301, 302, 303, 304, 307, 308, // Redirection messages
+    401, 403, 405, 406, 407, 408, 409, // Client errors that may indicate the site exists
+    429, // Too Many Requests (might mean rate-limited but active)
+    500, 501, 502, 503, 504, 505, // Server errors
+];
+
+// HTTP Error Codes that indicate the site does not exist
+const INACTIVE_CODES: [u16; 3] = [
+    404, // Not Found
+    410, // Gone
+    451, // Unavailable For Legal Reasons
+];
+
+/// What an HTTP status code says about a subject
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CodeClass {
+    Active,
+    Inactive,
+}
+
+impl fmt::Display for CodeClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodeClass::Active => write!(f, "active"),
+            CodeClass::Inactive => write!(f, "inactive"),
+        }
+    }
+}
+
+impl FromStr for CodeClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "active" => Ok(CodeClass::Active),
+            "inactive" => Ok(CodeClass::Inactive),
+            _ => Err(format!("Invalid Status Code Class: {}", s)),
+        }
+    }
+}
+
+/// Codes a rule applies to, a single code (`403`) or a whole class (`5xx`)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CodePattern {
+    Exact(u16),
+    Range(u16),
+}
+
+impl fmt::Display for CodePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodePattern::Exact(code) => write!(f, "{}", code),
+            CodePattern::Range(hundreds) => write!(f, "{}xx", hundreds),
+        }
+    }
+}
+
+impl FromStr for CodePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid Status Code: {}", s);
+        let s = s.trim();
+
+        match s.to_ascii_lowercase().strip_suffix("xx") {
+            Some(hundreds) => match hundreds.parse::<u16>() {
+                Ok(hundreds @ 1..=5) => Ok(CodePattern::Range(hundreds)),
+                _ => Err(invalid()),
+            },
+            None => match s.parse::<u16>() {
+                Ok(code @ 100..=599) => Ok(CodePattern::Exact(code)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+/// Single `pattern=class` rule, e.g. `403=active` or `5xx=inactive`
+#[derive(Clone, Copy, Debug)]
+pub struct CodeRule {
+    pub pattern: CodePattern,
+    pub class: CodeClass,
+}
+
+impl FromStr for CodeRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, class) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid Status Code Rule: {}", s))?;
+        Ok(CodeRule {
+            pattern: pattern.parse()?,
+            class: class.trim().parse()?,
+        })
+    }
+}
+
+/// Classification of HTTP status codes, exact codes take precedence over ranges
+pub struct StatusCodes {
+    exact: HashMap<u16, CodeClass>,
+    ranges: HashMap<u16, CodeClass>,
+}
+
+impl StatusCodes {
+    /// Built-in classification with layers of rules applied in order, as
+    /// described on `Config`
+    pub fn from_layers(layers: &[Vec<CodeRule>]) -> Result<Self, String> {
+        let mut codes = StatusCodes::default();
+        for layer in layers {
+            codes.apply(layer)?;
+        }
+        Ok(codes)
+    }
+
+    /// Class of a status code, `None` when no rule covers it
+    pub fn classify(&self, code: u16) -> Option<CodeClass> {
+        self.exact
+            .get(&code)
+            .or_else(|| self.ranges.get(&(code / 100)))
+            .copied()
+    }
+
+    /// Apply one layer of rules, rejecting a code or range it defines twice
+    /// with different classes
+    fn apply(&mut self, rules: &[CodeRule]) -> Result<(), String> {
+        let mut layer: HashMap<CodePattern, CodeClass> = HashMap::new();
+        for rule in rules {
+            if let Some(class) = layer.insert(rule.pattern, rule.class) {
+                if class != rule.class {
+                    return Err(format!(
+                        "Conflicting Status Code Rules: {} is both {} and {}",
+                        rule.pattern, class, rule.class
+                    ));
+                }
+            }
+        }
+
+        // A range replaces the codes of lower layers it covers, exact codes of
+        // the same layer still refine it
+        for (pattern, class) in &layer {
+            if let CodePattern::Range(hundreds) = pattern {
+                self.exact.retain(|code, _| code / 100 != *hundreds);
+                self.ranges.insert(*hundreds, *class);
+            }
+        }
+        for (pattern, class) in &layer {
+            if let CodePattern::Exact(code) = pattern {
+                self.exact.insert(*code, *class);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for StatusCodes {
+    fn default() -> Self {
+        let active = ACTIVE_CODES.iter().map(|code| (*code, CodeClass::Active));
+        let inactive = INACTIVE_CODES
+            .iter()
+            .map(|code| (*code, CodeClass::Inactive));
+        StatusCodes {
+            exact: active.chain(inactive).collect(),
+            ranges: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(rules: &str) -> Vec<CodeRule> {
+        rules.split(',').map(|rule| rule.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn built_in_classification() {
+        let codes = StatusCodes::from_layers(&[]).unwrap();
+        assert_eq!(codes.classify(200), Some(CodeClass::Active));
+        assert_eq!(codes.classify(404), Some(CodeClass::Inactive));
+        assert_eq!(codes.classify(418), None);
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let codes =
+            StatusCodes::from_layers(&[rules("403=inactive,418=active"), rules("403=active")])
+                .unwrap();
+        assert_eq!(codes.classify(403), Some(CodeClass::Active));
+        assert_eq!(codes.classify(418), Some(CodeClass::Active));
+    }
+
+    #[test]
+    fn ranges_replace_lower_layers_and_exact_codes_refine_them() {
+        let codes = StatusCodes::from_layers(&[rules("5xx=inactive,503=active")]).unwrap();
+        assert_eq!(codes.classify(500), Some(CodeClass::Inactive));
+        assert_eq!(codes.classify(503), Some(CodeClass::Active));
+
+        let codes =
+            StatusCodes::from_layers(&[rules("5xx=inactive"), rules("502=active")]).unwrap();
+        assert_eq!(codes.classify(502), Some(CodeClass::Active));
+        assert_eq!(codes.classify(504), Some(CodeClass::Inactive));
+    }
+
+    #[test]
+    fn conflicting_rules_in_a_layer_are_rejected() {
+        assert!(StatusCodes::from_layers(&[rules("403=active,403=inactive")]).is_err());
+        assert!(StatusCodes::from_layers(&[rules("5xx=active,5xx=inactive")]).is_err());
+        assert!(StatusCodes::from_layers(&[rules("403=active,403=active")]).is_ok());
+    }
+
+    #[test]
+    fn parse_patterns() {
+        assert_eq!("5XX".parse(), Ok(CodePattern::Range(5)));
+        assert_eq!(" 403 ".parse(), Ok(CodePattern::Exact(403)));
+        assert!("6xx".parse::<CodePattern>().is_err());
+        assert!("99".parse::<CodePattern>().is_err());
+        assert!("403".parse::<CodeRule>().is_err());
+    }
+}
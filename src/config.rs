@@ -0,0 +1,109 @@
+use crate::codes::{CodeClass, CodeRule};
+use crate::errors::ErrorRule;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Settings read from the `--config` TOML file. Every setting is layered: the
+/// built-in defaults, then this file, then the CLI flags, each layer overriding
+/// the previous one, while rules contradicting each other within a layer are
+/// rejected
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub status_codes: StatusCodesConfig,
+    /// `[on_error]` table, e.g. `timeout = "retry"` and `nxdomain = "inactive"`
+    pub on_error: HashMap<String, String>,
+    pub http: HttpConfig,
+    pub parked: ParkedConfig,
+    pub soft_404: Soft404Config,
+}
+
+/// `[http]` table of client options, e.g. `user_agents = ["Mozilla/5.0"]`,
+/// `headers = { "Accept-Language" = "en" }`, `timeout = 10` (seconds) and
+/// `proxy = "socks5h://127.0.0.1:1080"`
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub user_agents: Vec<String>,
+    pub headers: HashMap<String, String>,
+    pub connect_timeout: Option<u64>,
+    pub timeout: Option<u64>,
+    pub max_redirects: Option<usize>,
+    pub proxy: Option<String>,
+    pub accept_invalid_certs: bool,
+}
+
+/// `[parked]` table, signatures added to the built-in ones, e.g.
+/// `body = ["domain for sale"]` and `nameservers = ["parking.example"]`
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParkedConfig {
+    pub body: Vec<String>,
+    pub nameservers: Vec<String>,
+}
+
+/// `[soft_404]` table, keywords added to the built-in ones, e.g.
+/// `keywords = ["seite nicht gefunden"]`
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Soft404Config {
+    pub keywords: Vec<String>,
+}
+
+/// `[status_codes]` table, e.g. `active = [403, "5xx"]` and `inactive = [404]`
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusCodesConfig {
+    pub active: Vec<CodeValue>,
+    pub inactive: Vec<CodeValue>,
+}
+
+/// Status code written either as a number or as a string such as `"5xx"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum CodeValue {
+    Code(u16),
+    Pattern(String),
+}
+
+impl Config {
+    /// Read and parse a TOML config file
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Config Read Failed: {}: {}", path, e))?;
+        toml::from_str(&contents).map_err(|e| format!("Config Parse Failed: {}: {}", path, e))
+    }
+
+    /// Status code rules of the `[status_codes]` table
+    pub fn status_code_rules(&self) -> Result<Vec<CodeRule>, String> {
+        let active = self
+            .status_codes
+            .active
+            .iter()
+            .map(|value| (value, CodeClass::Active));
+        let inactive = self
+            .status_codes
+            .inactive
+            .iter()
+            .map(|value| (value, CodeClass::Inactive));
+
+        active
+            .chain(inactive)
+            .map(|(value, class)| {
+                let pattern = match value {
+                    CodeValue::Code(code) => code.to_string().parse()?,
+                    CodeValue::Pattern(pattern) => pattern.parse()?,
+                };
+                Ok(CodeRule { pattern, class })
+            })
+            .collect()
+    }
+
+    /// Error rules of the `[on_error]` table
+    pub fn error_rules(&self) -> Result<Vec<ErrorRule>, String> {
+        self.on_error
+            .iter()
+            .map(|(kind, action)| format!("{}={}", kind, action).parse())
+            .collect()
+    }
+}
@@ -0,0 +1,254 @@
+use crate::errors::ErrorKind;
+use futures::future::join_all;
+use hickory_resolver::config::{NameServerConfig, ResolverConfig};
+use hickory_resolver::net::runtime::TokioRuntimeProvider;
+use hickory_resolver::proto::rr::RecordType;
+use hickory_resolver::TokioResolver;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+// Record types that indicate the domain is still alive
+const RECORD_TYPES: [RecordType; 5] = [
+    RecordType::A,
+    RecordType::AAAA,
+    RecordType::CNAME,
+    RecordType::NS,
+    RecordType::MX,
+];
+
+/// Result of a DNS lookup for a single subject
+pub struct DnsResult {
+    pub records: Vec<(RecordType, String)>,
+    /// Why no record was found, `None` when the domain simply has none
+    pub error: Option<ErrorKind>,
+}
+
+impl DnsResult {
+    /// Whether any of the queried record types returned an answer
+    pub fn is_active(&self) -> bool {
+        !self.records.is_empty()
+    }
+
+    /// Name servers from the NS records
+    pub fn nameservers(&self) -> Vec<String> {
+        self.records
+            .iter()
+            .filter(|(record_type, _)| *record_type == RecordType::NS)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    /// Addresses from the A and AAAA records
+    pub fn ips(&self) -> Vec<String> {
+        self.records
+            .iter()
+            .filter(|(record_type, _)| matches!(record_type, RecordType::A | RecordType::AAAA))
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+}
+
+/// DNS checker sharing a single resolver across every task
+pub struct DnsChecker {
+    resolver: TokioResolver,
+}
+
+impl DnsChecker {
+    /// Build a checker using the given upstream servers (`ip` or `ip:port`),
+    /// or the system configuration when none are given
+    pub fn new(servers: &[String], timeout: Duration) -> Result<Self, String> {
+        let mut builder = if servers.is_empty() {
+            TokioResolver::builder_tokio()
+                .map_err(|e| format!("DNS System Config Failed: {}", e))?
+        } else {
+            let name_servers = servers
+                .iter()
+                .map(|server| parse_name_server(server))
+                .collect::<Result<Vec<_>, _>>()?;
+            TokioResolver::builder_with_config(
+                ResolverConfig::from_name_servers(name_servers),
+                TokioRuntimeProvider::default(),
+            )
+        };
+
+        let options = builder.options_mut();
+        options.timeout = timeout;
+        options.attempts = 1;
+
+        let resolver = builder
+            .build()
+            .map_err(|e| format!("DNS Resolver Creation Failed: {}", e))?;
+
+        Ok(DnsChecker { resolver })
+    }
+
+    /// Query A, AAAA, CNAME, NS and MX records for a domain
+    pub async fn check_dns(&self, domain: &str, verbose: bool) -> DnsResult {
+        // A trailing dot skips the search domains of the system configuration
+        let fqdn = format!("{}.", domain.trim_end_matches('.'));
+
+        let lookups = RECORD_TYPES
+            .iter()
+            .map(|record_type| self.resolver.lookup(fqdn.as_str(), *record_type));
+
+        let mut records = vec![];
+        let mut nxdomain = false;
+        let mut failed = false;
+        for (record_type, lookup) in RECORD_TYPES.iter().zip(join_all(lookups).await) {
+            match lookup {
+                Ok(lookup) => records.extend(
+                    lookup
+                        .answers()
+                        .iter()
+                        .filter(|record| record.record_type() == *record_type)
+                        .map(|record| (*record_type, record.data.to_string())),
+                ),
+                Err(e) => {
+                    nxdomain |= e.is_nx_domain();
+                    failed |= !e.is_no_records_found();
+                    if verbose {
+                        println!("DNS {} lookup for {} failed: {}", record_type, domain, e);
+                    }
+                }
+            }
+        }
+
+        if verbose {
+            for (record_type, value) in &records {
+                println!("DNS {} record for {}: {}", record_type, domain, value);
+            }
+        }
+
+        let error = if !records.is_empty() {
+            None
+        } else if nxdomain {
+            Some(ErrorKind::Nxdomain)
+        } else if failed {
+            Some(ErrorKind::Dns)
+        } else {
+            None
+        };
+
+        DnsResult { records, error }
+    }
+
+    /// Name servers of a domain, none when the lookup fails
+    pub async fn nameservers(&self, domain: &str, verbose: bool) -> Vec<String> {
+        let fqdn = format!("{}.", domain.trim_end_matches('.'));
+        match self.resolver.lookup(fqdn.as_str(), RecordType::NS).await {
+            Ok(lookup) => lookup
+                .answers()
+                .iter()
+                .filter(|record| record.record_type() == RecordType::NS)
+                .map(|record| record.data.to_string())
+                .collect(),
+            Err(e) => {
+                if verbose {
+                    println!("DNS NS lookup for {} failed: {}", domain, e);
+                }
+                vec![]
+            }
+        }
+    }
+}
+
+/// Parse `ip` or `ip:port` (`[ip]:port` for IPv6) into a name server config
+fn parse_name_server(server: &str) -> Result<NameServerConfig, String> {
+    let address = match server.parse::<SocketAddr>() {
+        Ok(address) => address,
+        Err(_) => server
+            .parse::<IpAddr>()
+            .map(|ip| SocketAddr::new(ip, 53))
+            .map_err(|_| format!("Invalid DNS Server: {}", server))?,
+    };
+
+    let mut config = NameServerConfig::udp_and_tcp(address.ip());
+    for connection in config.connections.iter_mut() {
+        connection.port = address.port();
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UdpSocket;
+
+    /// Local stand-in server answering `a.test` with an A record, `nodata.test`
+    /// without records, `fail.test` with SERVFAIL and any other name with NXDOMAIN
+    async fn stand_in() -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buffer).await {
+                if let Some(response) = answer(&buffer[..len]) {
+                    let _ = socket.send_to(&response, peer).await;
+                }
+            }
+        });
+        address
+    }
+
+    /// Response to a single-question query, extra sections are dropped
+    fn answer(query: &[u8]) -> Option<Vec<u8>> {
+        let mut end = 12;
+        let mut labels = vec![];
+        while *query.get(end)? != 0 {
+            let len = query[end] as usize;
+            let label = query.get(end + 1..end + 1 + len)?;
+            labels.push(String::from_utf8_lossy(label).to_lowercase());
+            end += len + 1;
+        }
+        let record_type = u16::from_be_bytes([*query.get(end + 1)?, *query.get(end + 2)?]);
+        let question = query.get(12..end + 5)?;
+
+        let (code, address) = match (labels.join(".").as_str(), record_type) {
+            ("a.test", 1) => (0, Some([93, 184, 216, 34])),
+            ("a.test" | "nodata.test", _) => (0, None),
+            ("fail.test", _) => (2, None),
+            _ => (3, None),
+        };
+        let mut response = query[..2].to_vec();
+        response.extend_from_slice(&(0x8180u16 | code).to_be_bytes());
+        response.extend_from_slice(&[0, 1, 0, address.is_some() as u8, 0, 0, 0, 0]);
+        response.extend_from_slice(question);
+        if let Some(address) = address {
+            response.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+            response.extend_from_slice(&address);
+        }
+        Some(response)
+    }
+
+    #[tokio::test]
+    async fn check_dns_against_stand_in_server() {
+        let server = stand_in().await;
+        let checker = DnsChecker::new(&[server.to_string()], Duration::from_secs(2)).unwrap();
+
+        let found = checker.check_dns("a.test", false).await;
+        assert!(found.is_active());
+        assert_eq!(found.ips(), vec!["93.184.216.34"]);
+        assert_eq!(found.error, None);
+
+        let nodata = checker.check_dns("nodata.test", false).await;
+        assert!(!nodata.is_active());
+        assert_eq!(nodata.error, None);
+
+        let nxdomain = checker.check_dns("missing.test", false).await;
+        assert!(!nxdomain.is_active());
+        assert_eq!(nxdomain.error, Some(ErrorKind::Nxdomain));
+
+        let servfail = checker.check_dns("fail.test", false).await;
+        assert!(!servfail.is_active());
+        assert_eq!(servfail.error, Some(ErrorKind::Dns));
+    }
+
+    #[test]
+    fn parse_name_server_ports() {
+        let port = |server: &str| parse_name_server(server).unwrap().connections[0].port;
+        assert_eq!(port("127.0.0.1"), 53);
+        assert_eq!(port("127.0.0.1:5353"), 5353);
+        assert_eq!(port("[::1]:5353"), 5353);
+        assert!(parse_name_server("resolver.example").is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+// Kinds of error retried unless a rule says otherwise, they are usually transient
+const RETRIED_KINDS: [ErrorKind; 3] = [
+    ErrorKind::Dns,
+    ErrorKind::ConnectionReset,
+    ErrorKind::Timeout,
+];
+
+/// Why a subject could not be reached
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// The domain does not exist
+    Nxdomain,
+    /// Name resolution failed for another reason (SERVFAIL, DNS timeout)
+    Dns,
+    /// Nothing listens on the port
+    ConnectionRefused,
+    /// The connection was reset or aborted
+    ConnectionReset,
+    /// No answer in time
+    Timeout,
+    /// TLS handshake or certificate failure
+    Tls,
+    /// The redirect limit was reached
+    TooManyRedirects,
+    /// Anything else
+    Other,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Nxdomain => "nxdomain",
+            ErrorKind::Dns => "dns",
+            ErrorKind::ConnectionRefused => "connection-refused",
+            ErrorKind::ConnectionReset => "connection-reset",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Tls => "tls",
+            ErrorKind::TooManyRedirects => "too-many-redirects",
+            ErrorKind::Other => "other",
+        }
+    }
+
+    /// Classify a failed request from its flags and its chain of sources
+    pub fn from_reqwest(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return ErrorKind::Timeout;
+        }
+        if error.is_redirect() {
+            return ErrorKind::TooManyRedirects;
+        }
+
+        let mut messages = String::new();
+        let mut source = error.source();
+        while let Some(error) = source {
+            if let Some(kind) = error
+                .downcast_ref::<io::Error>()
+                .and_then(ErrorKind::from_io)
+            {
+                return kind;
+            }
+            messages.push_str(&error.to_string().to_lowercase());
+            messages.push('\n');
+            source = error.source();
+        }
+
+        // The system resolver and the TLS backends only report these as text
+        if messages.contains("name or service not known")
+            || messages.contains("nodename nor servname")
+            || messages.contains("no such host")
+        {
+            ErrorKind::Nxdomain
+        } else if messages.contains("dns error") || messages.contains("failed to lookup address") {
+            ErrorKind::Dns
+        } else if ["certificate", "tls", "ssl", "handshake"]
+            .iter()
+            .any(|needle| messages.contains(needle))
+        {
+            ErrorKind::Tls
+        } else {
+            ErrorKind::Other
+        }
+    }
+
+    /// Classify the socket errors that have a kind of their own
+    pub fn from_io(error: &io::Error) -> Option<Self> {
+        match error.kind() {
+            io::ErrorKind::ConnectionRefused => Some(ErrorKind::ConnectionRefused),
+            io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted => {
+                Some(ErrorKind::ConnectionReset)
+            }
+            io::ErrorKind::TimedOut => Some(ErrorKind::Timeout),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Failed check, with the kind of failure and the reason
+#[derive(Debug)]
+pub struct CheckError {
+    pub kind: ErrorKind,
+    pub reason: String,
+}
+
+impl CheckError {
+    pub fn new(kind: ErrorKind, reason: String) -> Self {
+        CheckError { kind, reason }
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+/// What to do with a subject whose check failed with a given kind of error
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum ErrorAction {
+    /// Retry the check, the verdict is left to the defaults once attempts run out
+    Retry,
+    /// Report the subject ACTIVE
+    Active,
+    /// Report the subject INACTIVE
+    Inactive,
+    /// Report the subject UNKNOWN for review
+    Unknown,
+}
+
+impl ErrorAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorAction::Retry => "retry",
+            ErrorAction::Active => "active",
+            ErrorAction::Inactive => "inactive",
+            ErrorAction::Unknown => "unknown",
+        }
+    }
+}
+
+/// Single `kind=action` rule, e.g. `timeout=retry` or `nxdomain=inactive`
+#[derive(Clone, Copy, Debug)]
+pub struct ErrorRule {
+    pub kind: ErrorKind,
+    pub action: ErrorAction,
+}
+
+impl FromStr for ErrorRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, action) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid Error Rule: {}", s))?;
+        Ok(ErrorRule {
+            kind: ErrorKind::from_str(kind.trim(), true)
+                .map_err(|_| format!("Invalid Error Kind: {}", kind))?,
+            action: ErrorAction::from_str(action.trim(), true)
+                .map_err(|_| format!("Invalid Error Action: {}", action))?,
+        })
+    }
+}
+
+/// Action taken for each kind of error, kinds without a rule keep the default
+/// verdict
+pub struct ErrorRules {
+    actions: HashMap<ErrorKind, ErrorAction>,
+}
+
+impl ErrorRules {
+    /// Built-in retries, then the error rules of every layer
+    pub fn from_layers(layers: &[Vec<ErrorRule>]) -> Result<Self, String> {
+        let mut actions: HashMap<ErrorKind, ErrorAction> = RETRIED_KINDS
+            .iter()
+            .map(|kind| (*kind, ErrorAction::Retry))
+            .collect();
+        for layer in layers {
+            let mut defined: HashMap<ErrorKind, ErrorAction> = HashMap::new();
+            for rule in layer {
+                if let Some(action) = defined.insert(rule.kind, rule.action) {
+                    if action != rule.action {
+                        return Err(format!(
+                            "Conflicting Error Rules: {} is both {} and {}",
+                            rule.kind,
+                            action.as_str(),
+                            rule.action.as_str()
+                        ));
+                    }
+                }
+            }
+            actions.extend(defined);
+        }
+        Ok(ErrorRules { actions })
+    }
+
+    /// Action configured for a kind of error
+    pub fn action(&self, kind: ErrorKind) -> Option<ErrorAction> {
+        self.actions.get(&kind).copied()
+    }
+
+    /// Kinds of error whose checks are retried
+    pub fn retryable(&self) -> HashSet<ErrorKind> {
+        self.actions
+            .iter()
+            .filter(|(_, action)| **action == ErrorAction::Retry)
+            .map(|(kind, _)| *kind)
+            .collect()
+    }
+}
@@ -1,65 +1,416 @@
-use reqwest::Client;
-use std::time::Duration;
-
-// HTTP Error Codes that indicate the site exists
-const ACTIVE_CODES: [u16; 29] = [
-    200, 201, 202, 203, 204, 205, 206, // Successful responses
-    300, 301, 302, 303, 304, 307, 308, // Redirection messages
-    401, 403, 405, 406, 407, 408, 409, 410, // Client errors that may indicate the site exists
-    429, // Too Many Requests (might mean rate-limited but active)
-    500, 501, 502, 503, 504, 505, // Server errors
-];
-
-// HTTP Error Codes that indicate the site does not exist
-const INACTIVE_CODES: [u16; 3] = [
-    404, // Not Found
-    410, // Gone
-    451, // Unavailable For Legal Reasons
-];
-
-/// Check HTTP Status with support for redirects
-pub async fn check_http(url: &str, verbose: bool) -> Result<(bool, bool), String> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(5)) // Lower timeout for faster failure
-        .pool_max_idle_per_host(100) // Reuse connections
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .map_err(|e| format!("HTTP Client Creation Failed: {}", e))?;
-
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| format!("HTTP Status Failed: {}", e))?;
-    let final_url = response.url().clone();
-    let status_code = response.status().as_u16();
-    let is_active = ACTIVE_CODES.contains(&status_code);
-    let is_inactive = INACTIVE_CODES.contains(&status_code);
-    let redirected_to_www = final_url
-        .host_str()
-        .map_or(false, |host| host.starts_with("www."));
+use crate::codes::{CodeClass, StatusCodes};
+use crate::errors::ErrorKind;
+use crate::ratelimit::RateLimiter;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, LOCATION, RETRY_AFTER, USER_AGENT};
+use reqwest::{Client, Method, Proxy, RequestBuilder, Response, Url};
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::OwnedSemaphorePermit;
 
-    if verbose {
-        if is_active {
-            println!(
-                "HTTP check for {} succeeded with status code {}",
-                url, status_code
-            );
-        } else if is_inactive {
-            println!(
-                "HTTP check for {} failed with status code {}",
-                url, status_code
-            );
+// Time the requests of a check may take together unless configured, low for
+// faster failure
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Redirects followed at most before a check fails unless configured
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// HTTP method of the checks
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum RequestMethod {
+    /// HEAD, then GET when the server rejects it with 405 or 501
+    Head,
+    /// GET only
+    Get,
+}
+
+/// What to do with a subject redirected to another registrable domain
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum CrossDomainRedirect {
+    /// Judge the subject by the page it lands on
+    Allow,
+    /// Report the subject INACTIVE
+    Inactive,
+    /// Report the subject REDIRECTED
+    Redirected,
+}
+
+/// Single redirect of a chain, the URL that answered and its status code
+#[derive(Clone, Debug, Serialize)]
+pub struct Hop {
+    pub url: String,
+    pub status_code: u16,
+}
+
+/// What the HTTP check says about a URL
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum HttpOutcome {
+    /// Answered with an ACTIVE status code
+    Active,
+    /// Answered with an INACTIVE status code
+    Inactive,
+    /// Answered with a status code no rule classifies
+    Unknown,
+    /// No answer at all, with the kind of failure and the reason
+    Error(ErrorKind, String),
+}
+
+impl HttpOutcome {
+    /// How much the outcome says about the subject, to pick the best of
+    /// several probes
+    pub fn rank(&self) -> u8 {
+        match self {
+            HttpOutcome::Active => 3,
+            HttpOutcome::Unknown => 2,
+            HttpOutcome::Inactive => 1,
+            HttpOutcome::Error(..) => 0,
+        }
+    }
+}
+
+/// Result of an HTTP check for a single URL
+pub struct HttpResult {
+    pub outcome: HttpOutcome,
+    pub status_code: Option<u16>,
+    pub final_url: Option<String>,
+    /// Redirects followed before the final answer, or before the failure
+    pub redirects: Vec<Hop>,
+    /// Beginning of the page, only read when content is inspected
+    pub body: Option<String>,
+}
+
+/// How the shared client connects, identifies itself and reads pages
+pub struct HttpSettings {
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout: Duration,
+    /// Sent in turn, none leaves the `User-Agent` header out
+    pub user_agents: Vec<String>,
+    /// Extra headers of the requests to the probed origin, a later one replaces
+    /// an earlier one of the same name
+    pub headers: Vec<(String, String)>,
+    pub connect_timeout: Option<Duration>,
+    /// Bound of a whole check, redirects and body included, the pauses asked by
+    /// the rate limits excluded
+    pub timeout: Duration,
+    pub max_redirects: usize,
+    /// HTTP, HTTPS or SOCKS5 proxy URL
+    pub proxy: Option<String>,
+    pub accept_invalid_certs: bool,
+    pub method: RequestMethod,
+    /// Bytes read from the beginning of pages, 0 skips the body
+    pub body_bytes: usize,
+}
+
+/// HTTP checker sharing a single client, and its connection pool, across every task
+pub struct HttpChecker {
+    client: Client,
+    codes: StatusCodes,
+    limiter: RateLimiter,
+    headers: HeaderMap,
+    user_agents: Vec<HeaderValue>,
+    next_user_agent: AtomicUsize,
+    timeout: Duration,
+    max_redirects: usize,
+    method: RequestMethod,
+    body_bytes: usize,
+}
+
+impl HttpChecker {
+    pub fn new(
+        settings: HttpSettings,
+        codes: StatusCodes,
+        limiter: RateLimiter,
+    ) -> Result<Self, String> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &settings.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| format!("Invalid Header Name: {}: {}", name, e))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| format!("Invalid Header Value: {}: {}", value, e))?;
+            headers.insert(name, value);
+        }
+        let user_agents = settings
+            .user_agents
+            .iter()
+            .map(|agent| {
+                HeaderValue::from_str(agent)
+                    .map_err(|e| format!("Invalid User Agent: {}: {}", agent, e))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut builder = Client::builder()
+            .pool_max_idle_per_host(settings.pool_max_idle_per_host) // Reuse connections
+            .pool_idle_timeout(settings.pool_idle_timeout) // Close sockets nobody reuses
+            .redirect(reqwest::redirect::Policy::none()) // Followed by hand to record hops
+            .danger_accept_invalid_certs(settings.accept_invalid_certs);
+        if let Some(connect_timeout) = settings.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(proxy) = &settings.proxy {
+            let proxy =
+                Proxy::all(proxy).map_err(|e| format!("Invalid Proxy: {}: {}", proxy, e))?;
+            builder = builder.proxy(proxy);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| format!("HTTP Client Creation Failed: {}", e))?;
+
+        Ok(HttpChecker {
+            client,
+            codes,
+            limiter,
+            headers,
+            user_agents,
+            next_user_agent: AtomicUsize::new(0),
+            timeout: settings.timeout,
+            max_redirects: settings.max_redirects,
+            method: settings.method,
+            body_bytes: settings.body_bytes,
+        })
+    }
+
+    /// Check HTTP Status, following redirects and recording each of them. The
+    /// custom headers, credentials and cookies among them, only go to the
+    /// origin of the URL, like a browser would not leak them to another site
+    pub async fn check_http(&self, url: &str, verbose: bool) -> HttpResult {
+        let origin = Url::parse(url).ok().map(|url| url.origin());
+        let mut deadline = Instant::now() + self.timeout;
+        let mut redirects = vec![];
+        let mut current = url.to_string();
+        // The concurrency slot of the host is held until the body is read
+        let (response, _permit) = loop {
+            let same_origin = Url::parse(&current).ok().map(|url| url.origin()) == origin;
+            let (response, permit) = match self
+                .fetch(&current, same_origin, &mut deadline, verbose)
+                .await
+            {
+                Ok(fetched) => fetched,
+                Err(e) => {
+                    let kind = ErrorKind::from_reqwest(&e);
+                    let reason = format!("HTTP Status Failed: {}", e);
+                    return failure(url, kind, reason, redirects, verbose);
+                }
+            };
+            let Some(next) = location(&response) else {
+                break (response, permit);
+            };
+
+            redirects.push(Hop {
+                url: current,
+                status_code: response.status().as_u16(),
+            });
+            if redirects.len() > self.max_redirects {
+                let kind = ErrorKind::TooManyRedirects;
+                let reason = format!(
+                    "HTTP Status Failed: more than {} redirects",
+                    self.max_redirects
+                );
+                return failure(url, kind, reason, redirects, verbose);
+            }
+            if verbose {
+                println!("HTTP check for {} redirected to {}", url, next);
+            }
+            current = next.to_string();
+        };
+        let final_url = response.url().clone();
+        let status_code = response.status().as_u16();
+
+        let outcome = match self.codes.classify(status_code) {
+            Some(CodeClass::Active) => HttpOutcome::Active,
+            Some(CodeClass::Inactive) => HttpOutcome::Inactive,
+            None => HttpOutcome::Unknown,
+        };
+
+        if verbose {
+            match outcome {
+                HttpOutcome::Active => println!(
+                    "HTTP check for {} succeeded with status code {}",
+                    url, status_code
+                ),
+                HttpOutcome::Inactive => println!(
+                    "HTTP check for {} failed with status code {}",
+                    url, status_code
+                ),
+                _ => println!(
+                    "HTTP check for {} returned unclassified status code {}",
+                    url, status_code
+                ),
+            }
+        }
+
+        let body = if self.body_bytes > 0 {
+            Some(read_prefix(response, self.body_bytes).await)
         } else {
-            println!(
-                "HTTP check for {} returned status code {}",
-                url, status_code
-            );
+            None
+        };
+
+        HttpResult {
+            outcome,
+            status_code: Some(status_code),
+            final_url: Some(final_url.to_string()),
+            redirects,
+            body,
+        }
+    }
+
+    /// Fetch a single URL without following redirects, a server asking to slow
+    /// down pauses every request to its host and this one gets another chance
+    /// when the pause is short enough
+    async fn fetch(
+        &self,
+        url: &str,
+        same_origin: bool,
+        deadline: &mut Instant,
+        verbose: bool,
+    ) -> reqwest::Result<(Response, Option<OwnedSemaphorePermit>)> {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+
+        let (response, permit) = self.send(url, &host, same_origin, deadline).await?;
+        if let Some(delay) = retry_after(&response) {
+            self.limiter.block(&host, delay);
+            if delay <= self.limiter.max_retry_after {
+                if verbose {
+                    println!("HTTP check for {} retried after {:?}", url, delay);
+                }
+                // The slot goes back before queueing for another one
+                drop((response, permit));
+                return self.send(url, &host, same_origin, deadline).await;
+            }
+        }
+        Ok((response, permit))
+    }
+
+    /// Send a request once the rate limits of its host allow it, pages whose
+    /// content is inspected are always fetched with GET. Waiting for the rate
+    /// limits pushes the deadline of the check back, the response comes with
+    /// the concurrency slot of the host it holds
+    async fn send(
+        &self,
+        url: &str,
+        host: &str,
+        same_origin: bool,
+        deadline: &mut Instant,
+    ) -> reqwest::Result<(Response, Option<OwnedSemaphorePermit>)> {
+        let waiting = Instant::now();
+        let permit = self.limiter.acquire(host).await;
+        *deadline += waiting.elapsed();
+
+        if self.method == RequestMethod::Head && self.body_bytes == 0 {
+            let response = self
+                .request(Method::HEAD, url, same_origin, *deadline)
+                .send()
+                .await?;
+            if !matches!(response.status().as_u16(), 405 | 501) {
+                return Ok((response, permit));
+            }
+        }
+        let response = self
+            .request(Method::GET, url, same_origin, *deadline)
+            .send()
+            .await?;
+        Ok((response, permit))
+    }
+
+    /// Request carrying the next user agent of the rotation, the custom headers
+    /// when it goes to the probed origin, and the time left before the deadline
+    fn request(
+        &self,
+        method: Method,
+        url: &str,
+        same_origin: bool,
+        deadline: Instant,
+    ) -> RequestBuilder {
+        let mut request = self
+            .client
+            .request(method, url)
+            .timeout(deadline.saturating_duration_since(Instant::now()));
+        if same_origin {
+            request = request.headers(self.headers.clone());
+        }
+        if self.user_agents.is_empty() {
+            return request;
         }
-        if redirected_to_www {
-            println!("Redirected to www: {}", final_url);
+        let next = self.next_user_agent.fetch_add(1, Ordering::Relaxed);
+        request.header(USER_AGENT, &self.user_agents[next % self.user_agents.len()])
+    }
+}
+
+/// Parse a `Name: value` header
+pub fn parse_header(header: &str) -> Result<(String, String), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid Header: {}", header))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+/// Result of a check that got no final answer
+fn failure(
+    url: &str,
+    kind: ErrorKind,
+    reason: String,
+    redirects: Vec<Hop>,
+    verbose: bool,
+) -> HttpResult {
+    if verbose {
+        println!("HTTP check for {} failed ({}): {}", url, kind, reason);
+    }
+    HttpResult {
+        outcome: HttpOutcome::Error(kind, reason),
+        status_code: None,
+        final_url: None,
+        redirects,
+        body: None,
+    }
+}
+
+/// Target of a redirect response, relative locations are resolved against the
+/// URL that answered
+fn location(response: &Response) -> Option<Url> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    response.url().join(location).ok()
+}
+
+/// Read at most `max_bytes` of a body, a broken body counts as empty from
+/// where it broke
+async fn read_prefix(mut response: Response, max_bytes: usize) -> String {
+    let mut body = vec![];
+    while body.len() < max_bytes {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                let take = chunk.len().min(max_bytes - body.len());
+                body.extend_from_slice(&chunk[..take]);
+            }
+            _ => break,
         }
     }
+    String::from_utf8_lossy(&body).into_owned()
+}
+
+/// Delay asked by the `Retry-After` header of a 429 or 503 response, in
+/// seconds or as an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    if !matches!(response.status().as_u16(), 429 | 503) {
+        return None;
+    }
 
-    Ok((is_active, redirected_to_www))
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value).ok()?;
+            Some(
+                (date.with_timezone(&Utc) - Utc::now())
+                    .to_std()
+                    .unwrap_or_default(),
+            )
+        }
+    }
 }
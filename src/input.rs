@@ -0,0 +1,390 @@
+use clap::ValueEnum;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc::Sender;
+
+// Number of lines sampled to detect the input format
+const DETECTION_SAMPLE: usize = 100;
+
+// Hostnames found in every default hosts file, never worth testing
+const HOSTS_RESERVED: [&str; 7] = [
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "0.0.0.0",
+];
+
+/// Format of the input file
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum InputFormat {
+    /// One subject per line
+    Plain,
+    /// `0.0.0.0 example.com` entries
+    Hosts,
+    /// Adblock Plus `||example.com^` network rules
+    Adblock,
+    /// `address=/example.com/0.0.0.0` entries
+    Dnsmasq,
+    /// `local-zone: "example.com" always_nxdomain` entries
+    Unbound,
+    /// Response Policy Zone records
+    Rpz,
+}
+
+impl InputFormat {
+    /// Detect the format from a sample of lines, defaulting to plain
+    pub fn detect<'a>(lines: impl Iterator<Item = &'a str>) -> InputFormat {
+        let candidates = [
+            InputFormat::Hosts,
+            InputFormat::Adblock,
+            InputFormat::Dnsmasq,
+            InputFormat::Unbound,
+            InputFormat::Rpz,
+        ];
+        let mut scores = [0; 5];
+
+        for line in lines.map(str::trim).filter(|line| !line.is_empty()) {
+            for (format, score) in candidates.iter().zip(scores.iter_mut()) {
+                if format.is_typical(line) {
+                    *score += 1;
+                }
+            }
+        }
+
+        candidates
+            .into_iter()
+            .zip(scores)
+            .filter(|(_, score)| *score > 0)
+            .max_by_key(|(_, score)| *score)
+            .map_or(InputFormat::Plain, |(format, _)| format)
+    }
+
+    /// Whether a line looks like this format
+    fn is_typical(&self, line: &str) -> bool {
+        match self {
+            InputFormat::Plain => false,
+            InputFormat::Hosts => {
+                let mut tokens = line.split_whitespace();
+                tokens.next().is_some_and(|ip| ip.parse::<IpAddr>().is_ok())
+                    && tokens.next().is_some()
+            }
+            InputFormat::Adblock => {
+                line.starts_with("||") || line.starts_with("[Adblock") || line.starts_with('!')
+            }
+            InputFormat::Dnsmasq => ["address=/", "server=/", "local=/"]
+                .iter()
+                .any(|prefix| line.starts_with(prefix)),
+            InputFormat::Unbound => ["local-zone:", "local-data:", "server:"]
+                .iter()
+                .any(|prefix| line.starts_with(prefix)),
+            InputFormat::Rpz => {
+                line.starts_with("$TTL")
+                    || line.starts_with("$ORIGIN")
+                    || line.split_whitespace().any(|token| token == "CNAME")
+            }
+        }
+    }
+
+    /// Extract the subjects to test from a single line
+    pub fn parse_line(&self, line: &str) -> Vec<String> {
+        match self {
+            InputFormat::Plain => parse_plain(line),
+            InputFormat::Hosts => parse_hosts(line),
+            InputFormat::Adblock => parse_adblock(line),
+            InputFormat::Dnsmasq => parse_dnsmasq(line),
+            InputFormat::Unbound => parse_unbound(line),
+            InputFormat::Rpz => parse_rpz(line),
+        }
+    }
+}
+
+/// Read the input file lazily and send its subjects, skipping the completed ones.
+/// The format is detected from the first lines when not given. Stops early once
+/// every receiver is gone.
+pub async fn stream_subjects(
+    path: &str,
+    format: Option<InputFormat>,
+    completed: &HashSet<String>,
+    sender: Sender<String>,
+    verbose: bool,
+) -> Result<(), String> {
+    let file = File::open(path)
+        .await
+        .map_err(|e| format!("Input File Open Failed: {}: {}", path, e))?;
+    let mut lines = BufReader::new(file).lines();
+
+    // Only the detection sample is buffered, the rest is read as workers free up
+    let mut sample = vec![];
+    if format.is_none() {
+        while sample.len() < DETECTION_SAMPLE {
+            match lines.next_line().await.map_err(|e| e.to_string())? {
+                Some(line) => sample.push(line),
+                None => break,
+            }
+        }
+    }
+    let format = format.unwrap_or_else(|| InputFormat::detect(sample.iter().map(String::as_str)));
+    if verbose {
+        println!("Input format: {:?}", format);
+    }
+
+    for line in sample {
+        for subject in format.parse_line(&line) {
+            if !completed.contains(&subject) && sender.send(subject).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    while let Some(line) = lines.next_line().await.map_err(|e| e.to_string())? {
+        for subject in format.parse_line(&line) {
+            if !completed.contains(&subject) && sender.send(subject).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Send subjects that are already known, skipping the completed ones
+pub async fn send_subjects(
+    subjects: Vec<String>,
+    completed: &HashSet<String>,
+    sender: Sender<String>,
+) -> Result<(), String> {
+    for subject in subjects {
+        if !completed.contains(&subject) && sender.send(subject).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Plain lines, `# comment` lines and trailing comments are dropped but `#.com`
+/// is kept so the syntax stage reports it
+fn parse_plain(line: &str) -> Vec<String> {
+    let line = match line.find(" #").or_else(|| line.find("\t#")) {
+        Some(index) => &line[..index],
+        None => line,
+    };
+    let line = line.trim();
+
+    if line.is_empty() || line == "#" || line.starts_with("# ") || line.starts_with("#\t") {
+        return vec![];
+    }
+    vec![line.to_string()]
+}
+
+/// `<ip> <hostname>...` lines, a lone hostname is accepted as well
+fn parse_hosts(line: &str) -> Vec<String> {
+    let line = line.split('#').next().unwrap_or_default();
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+
+    let hostnames = match tokens.first() {
+        Some(first) if first.parse::<IpAddr>().is_ok() => &tokens[1..],
+        _ => &tokens[..],
+    };
+
+    hostnames
+        .iter()
+        .filter(|hostname| !HOSTS_RESERVED.contains(&hostname.to_lowercase().as_str()))
+        .map(|hostname| hostname.to_string())
+        .collect()
+}
+
+/// `||example.com^` blocking rules, exceptions and cosmetic rules are skipped
+fn parse_adblock(line: &str) -> Vec<String> {
+    let line = line.trim();
+    if line.is_empty()
+        || line.starts_with('!')
+        || line.starts_with('[')
+        || line.starts_with("@@")
+        || line.contains("##")
+        || line.contains("#@#")
+    {
+        return vec![];
+    }
+
+    // Options like `$third-party` don't change the blocked subject
+    let rule = line.split('$').next().unwrap_or_default();
+
+    if let Some(domain) = rule.strip_prefix("||") {
+        let domain = domain.trim_end_matches(['^', '|']);
+        if !domain.is_empty() && !domain.contains(['*', '^', '/']) {
+            return vec![domain.to_string()];
+        }
+    } else if let Some(url) = rule.strip_prefix('|') {
+        let url = url.trim_end_matches(['^', '|']);
+        if url.starts_with("http://") || url.starts_with("https://") {
+            return vec![url.to_string()];
+        }
+    }
+
+    vec![]
+}
+
+/// `address=/example.com/0.0.0.0` lines, one entry can list several domains
+fn parse_dnsmasq(line: &str) -> Vec<String> {
+    let line = line.split('#').next().unwrap_or_default().trim();
+    let Some((key, value)) = line.split_once('=') else {
+        return vec![];
+    };
+    if !["address", "server", "local"].contains(&key.trim()) {
+        return vec![];
+    }
+
+    // The last segment is the answer or upstream server, not a domain
+    let segments: Vec<&str> = value.trim().trim_start_matches('/').split('/').collect();
+    segments[..segments.len() - 1]
+        .iter()
+        .filter(|domain| !domain.is_empty())
+        .map(|domain| domain.to_string())
+        .collect()
+}
+
+/// `local-zone: "example.com" <type>` and `local-data: "example.com A ..."` lines
+fn parse_unbound(line: &str) -> Vec<String> {
+    let line = line.split('#').next().unwrap_or_default().trim();
+    let Some((key, value)) = line.split_once(':') else {
+        return vec![];
+    };
+    if !["local-zone", "local-data"].contains(&key.trim()) {
+        return vec![];
+    }
+
+    value
+        .split_whitespace()
+        .next()
+        .map(|domain| domain.trim_matches('"').trim_end_matches('.').to_string())
+        .into_iter()
+        .collect()
+}
+
+/// `example.com CNAME .` records, wildcards are covered by their parent record
+fn parse_rpz(line: &str) -> Vec<String> {
+    // Continuation lines of multi-line records start with whitespace
+    if line.starts_with([' ', '\t']) {
+        return vec![];
+    }
+
+    let line = line.split(';').next().unwrap_or_default();
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let Some(owner) = tokens.first() else {
+        return vec![];
+    };
+
+    if owner.starts_with(['$', '@', '*']) || !tokens.contains(&"CNAME") {
+        return vec![];
+    }
+    vec![owner.trim_end_matches('.').to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_plain_lines() {
+        assert_eq!(parse_plain("example.com # note"), vec!["example.com"]);
+        assert_eq!(
+            parse_plain("  https://example.com/#top  "),
+            vec!["https://example.com/#top"]
+        );
+        assert!(parse_plain("# comment").is_empty());
+        assert!(parse_plain("   ").is_empty());
+    }
+
+    #[test]
+    fn parse_hosts_lines() {
+        assert_eq!(
+            parse_hosts("0.0.0.0 ads.example.com tracker.example.com # ads"),
+            vec!["ads.example.com", "tracker.example.com"]
+        );
+        assert_eq!(parse_hosts("ads.example.com"), vec!["ads.example.com"]);
+        assert!(parse_hosts("127.0.0.1 localhost").is_empty());
+        assert!(parse_hosts("# 0.0.0.0 example.com").is_empty());
+    }
+
+    #[test]
+    fn parse_adblock_rules() {
+        assert_eq!(parse_adblock("||ads.example.com^"), vec!["ads.example.com"]);
+        assert_eq!(
+            parse_adblock("||ads.example.com^$third-party"),
+            vec!["ads.example.com"]
+        );
+        assert_eq!(
+            parse_adblock("|https://example.com/ad.js|"),
+            vec!["https://example.com/ad.js"]
+        );
+        assert!(parse_adblock("! comment").is_empty());
+        assert!(parse_adblock("@@||example.com^").is_empty());
+        assert!(parse_adblock("example.com##.banner").is_empty());
+        assert!(parse_adblock("||example.com/path^").is_empty());
+    }
+
+    #[test]
+    fn parse_dnsmasq_lines() {
+        assert_eq!(
+            parse_dnsmasq("address=/ads.example.com/#"),
+            vec!["ads.example.com"]
+        );
+        assert_eq!(
+            parse_dnsmasq("server=/a.example/b.example/1.1.1.1"),
+            vec!["a.example", "b.example"]
+        );
+        assert!(parse_dnsmasq("cache-size=1000").is_empty());
+    }
+
+    #[test]
+    fn parse_unbound_lines() {
+        assert_eq!(
+            parse_unbound("  local-zone: \"ads.example.com.\" always_nxdomain"),
+            vec!["ads.example.com"]
+        );
+        assert_eq!(
+            parse_unbound("local-data: \"a.example A 0.0.0.0\""),
+            vec!["a.example"]
+        );
+        assert!(parse_unbound("server:").is_empty());
+    }
+
+    #[test]
+    fn parse_rpz_lines() {
+        assert_eq!(
+            parse_rpz("ads.example.com CNAME ."),
+            vec!["ads.example.com"]
+        );
+        assert_eq!(
+            parse_rpz("ads.example.com. 300 IN CNAME . ; ads"),
+            vec!["ads.example.com"]
+        );
+        assert!(parse_rpz("*.ads.example.com CNAME .").is_empty());
+        assert!(parse_rpz("$TTL 300").is_empty());
+        assert!(parse_rpz("  NS localhost.").is_empty());
+    }
+
+    #[test]
+    fn detect_formats() {
+        let detect = |text: &str| InputFormat::detect(text.lines());
+        assert_eq!(
+            detect("0.0.0.0 a.example\n0.0.0.0 b.example"),
+            InputFormat::Hosts
+        );
+        assert_eq!(
+            detect("[Adblock Plus 2.0]\n||a.example^"),
+            InputFormat::Adblock
+        );
+        assert_eq!(detect("address=/a.example/#"), InputFormat::Dnsmasq);
+        assert_eq!(
+            detect("server:\n  local-zone: \"a.example.\" always_nxdomain"),
+            InputFormat::Unbound
+        );
+        assert_eq!(detect("$TTL 300\na.example CNAME ."), InputFormat::Rpz);
+        assert_eq!(detect("a.example\nhttps://b.example/"), InputFormat::Plain);
+    }
+}
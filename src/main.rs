@@ -1,31 +1,130 @@
+extern crate chrono;
 extern crate clap;
 extern crate colored;
 extern crate futures;
+extern crate hickory_resolver;
 extern crate reqwest;
 extern crate tokio;
 
+mod cache;
+mod checkpoint;
+mod codes;
+mod config;
+mod dns;
+mod errors;
 mod http;
+mod input;
+mod output;
+mod parked;
+mod probe;
+mod ratelimit;
+mod report;
+mod retry;
+mod shared;
+mod soft404;
+mod status;
+mod syntax;
+mod tld;
+mod whois;
+mod writer;
 
-use clap::Parser;
-use colored::*;
-use std::fs::{remove_file, OpenOptions};
-use std::io::Write;
+use clap::{Parser, ValueEnum};
+use codes::{CodeRule, StatusCodes};
+use config::Config;
+use errors::{ErrorAction, ErrorKind, ErrorRule, ErrorRules};
+use futures::future::join_all;
+use http::{CrossDomainRedirect, HttpOutcome, HttpResult, HttpSettings, RequestMethod};
+use input::InputFormat;
+use output::OutputFormat;
+use parked::ParkedDetector;
+use probe::{ProbeStrategy, Target};
+use ratelimit::RateLimiter;
+use report::{Record, ResultsFormat, StatusSource};
+use reqwest::Url;
+use retry::RetryPolicy;
+use soft404::Soft404Detector;
+use status::Status;
+use std::collections::HashSet;
+use std::fs::remove_file;
+use std::pin::pin;
 use std::sync::Arc;
-use tokio::sync::Semaphore;
-use tokio::task;
+use std::time::{Duration, Instant};
+use syntax::SubjectKind;
+use tld::Section;
+use tokio::signal;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::{self, AbortHandle};
+use writer::{Outcome, WriterSettings};
 
 /// CLI Arguments definition using Clap
 #[derive(Parser)]
 struct Args {
-    /// Input file containing the list of domains or URLs to check
-    #[arg(short, long)]
-    input_file: String,
+    /// TOML config file, its settings are overridden by the matching flags
+    #[arg(long)]
+    config: Option<String>,
+
+    /// Input file containing the list of domains or URLs to check, in retest mode
+    /// defaults to the INACTIVE subjects of the cache
+    #[arg(short, long, required_unless_present = "retest")]
+    input_file: Option<String>,
+
+    /// Format of the input file, detected from its first lines when omitted
+    #[arg(long, value_enum)]
+    input_format: Option<InputFormat>,
 
     /// Output file to write the results
     #[arg(short, long)]
     output_file: String,
 
-    /// Excluded output files [ACTIVE, INACTIVE]
+    /// Formats of the per-status output files, comma separated
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "plain")]
+    output_format: Vec<OutputFormat>,
+
+    /// File receiving one structured record per subject
+    #[arg(long)]
+    results_file: Option<String>,
+
+    /// Format of the structured results file
+    #[arg(long, value_enum, default_value = "jsonl")]
+    results_format: ResultsFormat,
+
+    /// File recording the completed subjects, defaults to `<output>.checkpoint`
+    #[arg(long)]
+    checkpoint_file: Option<String>,
+
+    /// Resume an interrupted run: skip the subjects in the checkpoint and append
+    /// to the existing output files instead of deleting them
+    #[arg(long)]
+    resume: bool,
+
+    /// SQLite file caching the last status of each subject between runs
+    #[arg(long)]
+    cache_file: Option<String>,
+
+    /// Days before a cached ACTIVE subject is checked again
+    #[arg(long, default_value_t = 7)]
+    cache_active_ttl: u64,
+
+    /// Hours before a cached INACTIVE subject is checked again
+    #[arg(long, default_value_t = 12)]
+    cache_inactive_ttl: u64,
+
+    /// Retest previously INACTIVE subjects, e.g. a former `<output>_INACTIVE.txt`
+    /// written to another output, and only report them DEAD after enough
+    /// consecutive failures
+    #[arg(long, requires = "cache_file")]
+    retest: bool,
+
+    /// Hours since the last check before an INACTIVE subject is retested
+    #[arg(long, default_value_t = 24)]
+    retest_delay: u64,
+
+    /// Consecutive failed checks before an INACTIVE subject is reported DEAD
+    #[arg(long, default_value_t = 3)]
+    retest_failures: u32,
+
+    /// Excluded output files [ACTIVE, INACTIVE, INVALID, DEAD, UNKNOWN, PARKED,
+    /// REDIRECTED]
     #[arg(short, long, default_value = "")]
     exclude: String,
 
@@ -36,76 +135,585 @@ struct Args {
     /// Verbose output level (1 or 2)
     #[arg(short, long, default_value_t = 1)]
     verbose_level: u8,
+
+    /// Schemes subjects without one are probed over
+    #[arg(long, value_enum, default_value = "http-first")]
+    probe: ProbeStrategy,
+
+    /// Also probe `www.example.com` when `example.com` fails, or the reverse
+    #[arg(long)]
+    www_fallback: bool,
+
+    /// What to do with subjects whose page redirects to another registrable
+    /// domain, such as a registrar or a parking service
+    #[arg(long, value_enum, default_value = "allow")]
+    cross_domain_redirect: CrossDomainRedirect,
+
+    /// Report ACTIVE subjects whose page or name servers match a parking service
+    /// or placeholder signature as PARKED
+    #[arg(long)]
+    detect_parked: bool,
+
+    /// Extra page signature of parked domains, can be repeated
+    #[arg(long = "parked-signature")]
+    parked_signatures: Vec<String>,
+
+    /// Extra name server domain of parking services, can be repeated
+    #[arg(long = "parked-nameserver")]
+    parked_nameservers: Vec<String>,
+
+    /// Report ACTIVE URLs whose title or main heading says "not found", or whose
+    /// page looks like the one served for a random path of the same host, as
+    /// INACTIVE
+    #[arg(long)]
+    detect_soft_404: bool,
+
+    /// Extra keyword of "not found" pages, can be repeated
+    #[arg(long = "soft-404-keyword")]
+    soft_404_keywords: Vec<String>,
+
+    /// HTTP method of the checks, HEAD skips the body but is replaced by GET
+    /// when the content of pages is inspected
+    #[arg(long, value_enum, default_value = "get")]
+    method: RequestMethod,
+
+    /// Bytes read at most from the beginning of a page, the body is only read
+    /// when its content is inspected
+    #[arg(long, default_value_t = 65536)]
+    max_body_bytes: usize,
+
+    /// Maximum HTTP requests per second across every host
+    #[arg(long)]
+    rate_limit: Option<f64>,
+
+    /// Maximum HTTP requests per second to a single host
+    #[arg(long)]
+    host_rate_limit: Option<f64>,
+
+    /// Maximum concurrent HTTP requests to a single host
+    #[arg(long)]
+    host_concurrency: Option<usize>,
+
+    /// Longest `Retry-After` pause, in seconds, a 429 or 503 response holds its
+    /// host for, the response is only retried when the pause fits
+    #[arg(long, default_value_t = 60)]
+    max_retry_after: u64,
+
+    /// User-Agent header of the requests, repeat it to rotate through several,
+    /// overrides the config file
+    #[arg(long = "user-agent")]
+    user_agents: Vec<String>,
+
+    /// Extra request header, e.g. `Accept-Language: en`, can be repeated,
+    /// overrides the config file header of the same name. Only sent to the
+    /// origin of the subject, not to the hosts it redirects to
+    #[arg(long = "header", value_parser = http::parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Timeout in seconds to establish a connection, bounded by `--timeout`
+    #[arg(long)]
+    connect_timeout: Option<u64>,
+
+    /// Timeout in seconds for a whole HTTP check, redirects and body included,
+    /// 5 unless set in the config file
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    /// Redirects followed at most before the check fails, 10 unless set in the
+    /// config file
+    #[arg(long)]
+    max_redirects: Option<usize>,
+
+    /// HTTP, HTTPS or SOCKS5 proxy for every request, e.g. `http://proxy:3128`
+    /// or `socks5h://127.0.0.1:1080`
+    #[arg(long)]
+    proxy: Option<String>,
+
+    /// Accept invalid TLS certificates (self-signed, expired, issued for another
+    /// host)
+    #[arg(long)]
+    accept_invalid_certs: bool,
+
+    /// Maximum number of idle connections kept per host
+    #[arg(long, default_value_t = 100)]
+    pool_max_idle_per_host: usize,
+
+    /// Seconds before an idle connection is closed
+    #[arg(long, default_value_t = 30)]
+    pool_idle_timeout: u64,
+
+    /// Classification of an HTTP status code or range, e.g. `403=active` or
+    /// `5xx=inactive`, comma separated or repeated, overrides the config file
+    #[arg(long = "status-code", value_delimiter = ',')]
+    status_codes: Vec<CodeRule>,
+
+    /// Action for a kind of network error, e.g. `timeout=retry` or
+    /// `nxdomain=inactive`, comma separated or repeated, overrides the config file.
+    /// Kinds: nxdomain, dns, connection-refused, connection-reset, timeout, tls,
+    /// too-many-redirects, other. Actions: retry, active, inactive, unknown
+    #[arg(long = "on-error", value_delimiter = ',')]
+    error_rules: Vec<ErrorRule>,
+
+    /// Extra attempts for a check failing with a retryable kind of error
+    /// (dns, connection-reset and timeout unless changed with `--on-error`)
+    #[arg(long, default_value_t = 1)]
+    retries: u32,
+
+    /// Milliseconds to wait before the first retry, doubled for each next one
+    #[arg(long, default_value_t = 500)]
+    retry_backoff: u64,
+
+    /// Maximum milliseconds to wait between two attempts
+    #[arg(long, default_value_t = 10000)]
+    retry_max_backoff: u64,
+
+    /// Random share of each wait, from 0 (none) to 1 (anywhere up to the full wait)
+    #[arg(long, default_value_t = 0.5)]
+    retry_jitter: f64,
+
+    /// DNS server to query instead of the system resolvers (ip or ip:port), can be repeated
+    #[arg(long = "dns-server")]
+    dns_servers: Vec<String>,
+
+    /// Timeout in seconds for each DNS query
+    #[arg(long, default_value_t = 5)]
+    dns_timeout: u64,
+
+    /// Disable DNS checks and rely on HTTP only
+    #[arg(long)]
+    no_dns: bool,
+
+    /// Look up WHOIS records and report expired domains as INACTIVE
+    #[arg(long)]
+    whois: bool,
+
+    /// Timeout in seconds for each WHOIS query
+    #[arg(long, default_value_t = 10)]
+    whois_timeout: u64,
+
+    /// Maximum concurrent queries to a single WHOIS server, busy clients get banned
+    #[arg(long, default_value_t = 2)]
+    whois_concurrency: usize,
+
+    /// Local TLD list, e.g. IANA's `tlds-alpha-by-domain.txt`, to use instead of
+    /// the embedded one derived from the Public Suffix List
+    #[arg(long)]
+    tld_file: Option<String>,
+
+    /// Local Public Suffix List to use instead of the embedded one
+    #[arg(long)]
+    suffix_file: Option<String>,
 }
 
-/// Main logic for checking a single domain or URL
-async fn check_domain_or_url(
-    input: String,
-    semaphore: Arc<Semaphore>,
-    output_file: String,
-    exclude: String,
+/// Settings of the retest mode
+struct Retest {
+    delay: Duration,
+    max_failures: u32,
+}
+
+/// Checkers and settings shared by every task
+struct Context {
+    writer: mpsc::Sender<Outcome>,
+    cache: Option<Arc<cache::Cache>>,
+    retest: Option<Retest>,
     verbose_level: u8,
-) -> Result<(), String> {
-    let permit = semaphore.acquire().await.map_err(|e| e.to_string())?;
+    http: http::HttpChecker,
+    probe: ProbeStrategy,
+    www_fallback: bool,
+    cross_domain_redirect: CrossDomainRedirect,
+    parked: Option<ParkedDetector>,
+    soft_404: Option<Soft404Detector>,
+    on_error: ErrorRules,
+    retry: RetryPolicy,
+    dns: Option<dns::DnsChecker>,
+    whois: Option<whois::WhoisChecker>,
+    suffixes: tld::SuffixList,
+}
+
+/// Main logic for checking a single domain or URL
+async fn check_domain_or_url(input: String, context: &Context) -> Result<(), String> {
+    let verbose_level = context.verbose_level;
 
     if verbose_level > 1 {
         println!("Checking: {}", input);
     }
 
-    let url = if input.starts_with("http://") || input.starts_with("https://") {
-        input.clone()
-    } else {
-        format!("http://{}", input)
-    };
+    let started = Instant::now();
+    let mut record = Record::new(&input);
+    let kind = syntax::classify(&input, &context.suffixes);
+    match (kind, &context.retest, &context.cache) {
+        (Some(kind), Some(retest), Some(cache)) => {
+            retest_status(&input, kind, context, retest, cache, &mut record).await
+        }
+        (Some(kind), _, cache) => {
+            let cached = match cache {
+                Some(cache) => cache.get(&input).await.unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    None
+                }),
+                None => None,
+            };
+
+            match cached {
+                Some(status) => {
+                    record.status = status;
+                    record.source = StatusSource::Cache;
+                }
+                None => determine_status(&input, kind, context, &mut record).await,
+            }
+        }
+        (None, _, _) => {}
+    }
+    record.timings.total_ms = started.elapsed().as_millis() as u64;
+
+    if verbose_level > 0 {
+        println!("{}: {}", input, record.status.colored());
+    }
 
-    let (http_success, redirected_to_www) = http::check_http(&url, verbose_level > 1)
+    // Files are only touched by the writer task
+    context
+        .writer
+        .send(Outcome { record, kind })
         .await
-        .unwrap_or((false, false));
+        .map_err(|_| "Writer Task Stopped".to_string())?;
 
-    let status = if http_success || redirected_to_www {
-        "ACTIVE"
-    } else {
-        "INACTIVE"
+    if verbose_level > 1 {
+        println!("Finished checking: {}", input);
+    }
+
+    Ok(())
+}
+
+/// Retest a previously INACTIVE subject once its delay has passed, only
+/// declaring it DEAD after enough consecutive failures
+async fn retest_status(
+    input: &str,
+    kind: SubjectKind,
+    context: &Context,
+    retest: &Retest,
+    cache: &Arc<cache::Cache>,
+    record: &mut Record,
+) {
+    let entry = cache.entry(input).await.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        None
+    });
+
+    // Checked too recently, carried over as is so it stays in the dataset
+    if let Some(entry) = entry.as_ref().filter(|entry| entry.age() < retest.delay) {
+        record.status = entry.status;
+        record.source = StatusSource::Cache;
+        return;
+    }
+
+    determine_status(input, kind, context, record).await;
+
+    let failures = entry.map_or(0, |entry| entry.failures) + 1;
+    if record.status == Status::Inactive && failures >= retest.max_failures {
+        record.status = Status::Dead;
+    }
+}
+
+/// Decide the status of a syntactically valid subject, filling in the record
+async fn determine_status(input: &str, kind: SubjectKind, context: &Context, record: &mut Record) {
+    let verbose_level = context.verbose_level;
+
+    // IP ranges can't be probed as a single host, a valid range is kept as is
+    if kind.is_range() {
+        record.status = Status::Active;
+        return;
+    }
+
+    // Expired registrations are inactive whatever the HTTP or DNS state,
+    // subdomains share the registration of their registrable domain
+    let registrable = context
+        .suffixes
+        .registrable_domain(input)
+        .filter(|_| kind.is_domain());
+    let private = context.suffixes.public_suffix(input).section == Section::Private;
+    if let (Some(whois), Some(domain)) = (&context.whois, registrable.filter(|_| !private)) {
+        let started = Instant::now();
+        let (result, attempts) = context
+            .retry
+            .run(
+                || whois.check_whois(&domain, verbose_level > 1),
+                |result| result.as_ref().err().map(|e| e.kind),
+            )
+            .await;
+        record.timings.whois_ms = Some(started.elapsed().as_millis() as u64);
+        record.attempts.whois = Some(attempts);
+
+        match result {
+            Ok(result) if result.is_expired() => {
+                record.status = Status::Inactive;
+                record.source = StatusSource::Whois;
+                return;
+            }
+            Ok(_) => {}
+            // A failed lookup leaves the verdict to the later stages
+            Err(e) => {
+                if verbose_level > 1 {
+                    println!("WHOIS check for {} failed: {}", input, e);
+                }
+            }
+        }
+    }
+
+    // Only domains are checked against DNS, URLs need a working page
+    let dns_check = async {
+        let started = Instant::now();
+        let result = match &context.dns {
+            Some(dns) if kind.is_domain() => Some(
+                context
+                    .retry
+                    .run(
+                        || dns.check_dns(input, verbose_level > 1),
+                        |result| result.error,
+                    )
+                    .await,
+            ),
+            _ => None,
+        };
+        (result, started.elapsed())
     };
+    let http_check = async {
+        let started = Instant::now();
+        let targets = context.probe.targets(input, kind, context.www_fallback);
+        let result = probe_http(targets, context).await;
+        (result, started.elapsed())
+    };
+
+    let (((http_result, http_attempts, target), http_elapsed), (dns_result, dns_elapsed)) =
+        tokio::join!(http_check, dns_check);
+    record.timings.http_ms = Some(http_elapsed.as_millis() as u64);
+    record.attempts.http = Some(http_attempts);
+    record.scheme = target.scheme().map(str::to_string);
+    record.variant = target.variant;
 
-    if status != exclude {
-        let file_path = format!("{}_{}.txt", output_file, status);
-        let mut file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(&file_path)
-            .map_err(|e| e.to_string())?;
-        writeln!(file, "{}", input).map_err(|e| e.to_string())?;
+    let mut dns_success = false;
+    let mut dns_error = None;
+    let mut nameservers = vec![];
+    if let Some((result, attempts)) = dns_result {
+        record.timings.dns_ms = Some(dns_elapsed.as_millis() as u64);
+        record.attempts.dns = Some(attempts);
+        record.ips = result.ips();
+        dns_success = result.is_active();
+        dns_error = result.error;
+        nameservers = result.nameservers();
     }
 
-    if verbose_level > 0 {
-        let status_colored = match status {
-            "ACTIVE" => status.bold().green(),
-            "INACTIVE" => status.bold().red(),
-            _ => status.normal(),
+    record.http_status_code = http_result.status_code;
+    record.final_url = http_result.final_url;
+    record.redirects = http_result.redirects;
+    let dns_failure = dns_error.map(|kind| (kind, format!("DNS Lookup Failed: {}", kind)));
+    let http_failure = match &http_result.outcome {
+        // When it ran, our resolver has the final word on name resolution
+        HttpOutcome::Error(ErrorKind::Nxdomain | ErrorKind::Dns, reason)
+            if record.timings.dns_ms.is_some() =>
+        {
+            dns_failure
+                .clone()
+                .or_else(|| Some((ErrorKind::Dns, reason.clone())))
+        }
+        HttpOutcome::Error(kind, reason) => Some((*kind, reason.clone())),
+        _ => None,
+    };
+    let action = http_failure
+        .as_ref()
+        .and_then(|(kind, _)| context.on_error.action(*kind));
+    let error_source = match http_failure {
+        Some((ErrorKind::Nxdomain | ErrorKind::Dns, _)) => StatusSource::Dns,
+        _ => StatusSource::Http,
+    };
+
+    // An unclassified answer is left for review, a failed HTTP check follows
+    // its error rule, or without one leaves the verdict to DNS
+    (record.status, record.source) = match (http_result.outcome, action) {
+        (HttpOutcome::Active, _) => (Status::Active, StatusSource::Http),
+        (HttpOutcome::Unknown, _) => (Status::Unknown, StatusSource::Http),
+        (HttpOutcome::Error(..), Some(ErrorAction::Active)) => (Status::Active, error_source),
+        (HttpOutcome::Error(..), Some(ErrorAction::Inactive)) => (Status::Inactive, error_source),
+        (HttpOutcome::Error(..), Some(ErrorAction::Unknown)) => (Status::Unknown, error_source),
+        _ if dns_success => (Status::Active, StatusSource::Dns),
+        (HttpOutcome::Error(..), _) if record.timings.dns_ms.is_some() => {
+            (Status::Inactive, StatusSource::Dns)
+        }
+        _ => (Status::Inactive, StatusSource::Http),
+    };
+
+    // Only the failure the verdict rests on is reported, a subject found ACTIVE
+    // or UNKNOWN has none
+    let ruled = !matches!(action, None | Some(ErrorAction::Retry));
+    let failure = match (record.status, record.source) {
+        _ if ruled => http_failure,
+        (Status::Inactive, StatusSource::Dns) => dns_failure.or(http_failure),
+        (Status::Inactive, StatusSource::Http) => http_failure,
+        _ => None,
+    };
+    (record.error_kind, record.error) = failure.unzip();
+
+    // Landing on another site (a registrar, a parking service, a new owner)
+    // says little about the subject itself
+    let landed = record.source == StatusSource::Http
+        && matches!(record.status, Status::Active | Status::Unknown);
+    let cross_domain =
+        landed && is_cross_domain(&target.url, record.final_url.as_deref(), &context.suffixes);
+    match context.cross_domain_redirect {
+        CrossDomainRedirect::Inactive if cross_domain => record.status = Status::Inactive,
+        CrossDomainRedirect::Redirected if cross_domain => record.status = Status::Redirected,
+        _ => {}
+    }
+
+    // Parking pages answer like any live site, only their content or their
+    // name servers give them away. The name servers are those of the zone of
+    // the registrable domain, subdomains rarely have their own
+    if let (Some(parked), Status::Active) = (&context.parked, record.status) {
+        let body = http_result.body.as_deref().unwrap_or_default();
+        let signature = match parked.match_body(body) {
+            Some(signature) => Some((signature, StatusSource::Http)),
+            None => {
+                if let (Some(dns), SubjectKind::Subdomain) = (&context.dns, kind) {
+                    if let Some(domain) = context.suffixes.registrable_domain(input) {
+                        nameservers = dns.nameservers(&domain, verbose_level > 1).await;
+                    }
+                }
+                parked
+                    .match_nameservers(&nameservers)
+                    .map(|signature| (signature, StatusSource::Dns))
+            }
         };
-        println!("{}: {}", input, status_colored);
+        if let Some((signature, source)) = signature {
+            record.status = Status::Parked;
+            record.source = source;
+            record.signature = Some(signature.to_string());
+        }
     }
 
-    if verbose_level > 1 {
-        println!("Finished checking: {}", input);
+    // Missing pages of URLs sometimes answer with a success status
+    let answered = record.status == Status::Active && record.source == StatusSource::Http;
+    if let (Some(soft_404), SubjectKind::Url, true) = (&context.soft_404, kind, answered) {
+        let url = record.final_url.as_deref().unwrap_or_default();
+        let body = http_result.body.as_deref().unwrap_or_default();
+        if let Some(signature) = soft_404
+            .check(&target.url, url, body, &context.http, verbose_level > 1)
+            .await
+        {
+            record.status = Status::Inactive;
+            record.signature = Some(signature);
+        }
     }
+}
 
-    drop(permit); // Release semaphore permit
-    Ok(())
+/// Probe the URLs of a subject, in turn until one is ACTIVE or all at once with
+/// the `both` strategy, returning the best result, the total number of attempts
+/// and the target that gave it
+async fn probe_http(targets: Vec<Target>, context: &Context) -> (HttpResult, u32, Target) {
+    let probes = if context.probe == ProbeStrategy::Both {
+        join_all(targets.into_iter().map(|target| check_url(target, context))).await
+    } else {
+        // Hosts that don't exist, nor do their subdomains
+        let mut nxdomains: Vec<String> = vec![];
+        let mut probes = vec![];
+        for target in targets {
+            let host = Url::parse(&target.url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_default();
+            if nxdomains
+                .iter()
+                .any(|nxdomain| host == *nxdomain || host.ends_with(&format!(".{}", nxdomain)))
+            {
+                continue;
+            }
+
+            let probe = check_url(target, context).await;
+            match probe.1.outcome {
+                HttpOutcome::Active => {
+                    probes.push(probe);
+                    break;
+                }
+                HttpOutcome::Error(ErrorKind::Nxdomain, _) => nxdomains.push(host),
+                _ => {}
+            }
+            probes.push(probe);
+        }
+        probes
+    };
+
+    // The earliest target wins a tie
+    let attempts = probes.iter().map(|(_, _, attempts)| attempts).sum();
+    let (target, result, _) = probes
+        .into_iter()
+        .reduce(|best, probe| {
+            if probe.1.outcome.rank() > best.1.outcome.rank() {
+                probe
+            } else {
+                best
+            }
+        })
+        .expect("at least one URL is probed");
+    (result, attempts, target)
+}
+
+/// Whether a probed URL landed on another registrable domain
+fn is_cross_domain(url: &str, final_url: Option<&str>, suffixes: &tld::SuffixList) -> bool {
+    let site = |url: &str| {
+        Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| suffixes.site(host)))
+    };
+    match (site(url), final_url.and_then(site)) {
+        (Some(probed), Some(landed)) => probed != landed,
+        _ => false,
+    }
 }
 
-/// Delete output files if they exist
-fn delete_output_files(output_file: &str) {
-    let active_file = format!("{}_ACTIVE.txt", output_file);
-    let inactive_file = format!("{}_INACTIVE.txt", output_file);
+/// Check a single URL, retrying it as the retry policy allows
+async fn check_url(target: Target, context: &Context) -> (Target, HttpResult, u32) {
+    let (result, attempts) = context
+        .retry
+        .run(
+            || {
+                context
+                    .http
+                    .check_http(&target.url, context.verbose_level > 1)
+            },
+            |result| match result.outcome {
+                HttpOutcome::Error(kind, _) => Some(kind),
+                _ => None,
+            },
+        )
+        .await;
+    (target, result, attempts)
+}
 
-    if std::path::Path::new(&active_file).exists() {
-        remove_file(&active_file).unwrap();
+/// Paths of every output file of a run, whether they exist or not
+fn output_paths(output_file: &str, other_files: &[&String]) -> Vec<String> {
+    let mut paths = vec![];
+    for status in Status::ALL {
+        for format in OutputFormat::value_variants() {
+            paths.push(format.file_path(output_file, status));
+        }
     }
+    paths.extend(other_files.iter().map(|path| path.to_string()));
+    paths
+}
 
-    if std::path::Path::new(&inactive_file).exists() {
-        remove_file(&inactive_file).unwrap();
+/// Refuse an input file that the run would delete or append to, e.g. retesting
+/// `out_INACTIVE.txt` with `-o out`
+fn check_input_not_output(input_file: &str, output_paths: &[String]) -> Result<(), String> {
+    let Ok(input) = std::fs::canonicalize(input_file) else {
+        return Ok(());
+    };
+    match output_paths
+        .iter()
+        .find(|path| std::fs::canonicalize(path).is_ok_and(|path| path == input))
+    {
+        Some(path) => Err(format!(
+            "Input File Is Also An Output File: {}, choose another --output-file",
+            path
+        )),
+        None => Ok(()),
     }
 }
 
@@ -114,42 +722,260 @@ fn delete_output_files(output_file: &str) {
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse command-line arguments
     let args = Args::parse();
+    let config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
 
-    // Delete output files if they exist
-    delete_output_files(&args.output_file);
-
-    // Read input file
-    let contents = std::fs::read_to_string(args.input_file)?;
-    let inputs: Vec<String> = contents.lines().map(|s| s.to_string()).collect();
+    // Built-in classification, then the config file, then the CLI
+    let status_codes =
+        StatusCodes::from_layers(&[config.status_code_rules()?, args.status_codes.clone()])?;
+    let on_error = ErrorRules::from_layers(&[config.error_rules()?, args.error_rules.clone()])?;
+    if !(0.0..=1.0).contains(&args.retry_jitter) {
+        return Err(format!("Invalid Retry Jitter: {}", args.retry_jitter).into());
+    }
+    let retry = RetryPolicy {
+        retries: args.retries,
+        backoff: Duration::from_millis(args.retry_backoff),
+        max_backoff: Duration::from_millis(args.retry_max_backoff),
+        jitter: args.retry_jitter,
+        retryable: on_error.retryable(),
+    };
 
-    // Set concurrency limit
-    let semaphore = Arc::new(Semaphore::new(args.concurrency));
+    let checkpoint_file = args
+        .checkpoint_file
+        .clone()
+        .unwrap_or_else(|| checkpoint::default_path(&args.output_file));
 
-    // Run checks concurrently
-    let mut handles = vec![];
+    let other_files: Vec<&String> = args.results_file.iter().chain([&checkpoint_file]).collect();
+    let output_paths = output_paths(&args.output_file, &other_files);
+    if let Some(input_file) = &args.input_file {
+        check_input_not_output(input_file, &output_paths)?;
+    }
 
-    for input in inputs {
-        let sem_clone = semaphore.clone();
-        let output_file = args.output_file.clone();
-        let exclude = args.exclude.clone();
-        let verbose_level = args.verbose_level;
-        let handle = task::spawn(async move {
-            if let Err(e) =
-                check_domain_or_url(input, sem_clone, output_file, exclude, verbose_level).await
-            {
-                eprintln!("Error checking domain or URL: {}", e);
+    // Delete output files if they exist, unless resuming where they left off
+    let completed = if args.resume {
+        checkpoint::load_completed(&checkpoint_file)?
+    } else {
+        for file_path in &output_paths {
+            if std::path::Path::new(file_path).exists() {
+                remove_file(file_path)?;
             }
-        });
-        handles.push(handle);
+        }
+        HashSet::new()
+    };
+    if args.verbose_level > 1 && args.resume {
+        println!("Resuming, skipping {} completed subjects", completed.len());
     }
 
-    // Await all tasks
-    for handle in handles {
-        if let Err(e) = handle.await {
-            eprintln!("Task failed: {:?}", e);
+    // Share a single HTTP client, and its connection pool, across all tasks
+    // The CLI flags override the `[http]` table of the config file
+    let user_agents = if args.user_agents.is_empty() {
+        config.http.user_agents.clone()
+    } else {
+        args.user_agents.clone()
+    };
+    let headers = config
+        .http
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .chain(args.headers.iter().cloned())
+        .collect();
+    let http = http::HttpChecker::new(
+        HttpSettings {
+            pool_max_idle_per_host: args.pool_max_idle_per_host,
+            pool_idle_timeout: Duration::from_secs(args.pool_idle_timeout),
+            user_agents,
+            headers,
+            connect_timeout: args
+                .connect_timeout
+                .or(config.http.connect_timeout)
+                .map(Duration::from_secs),
+            timeout: args
+                .timeout
+                .or(config.http.timeout)
+                .map_or(http::DEFAULT_TIMEOUT, Duration::from_secs),
+            max_redirects: args
+                .max_redirects
+                .or(config.http.max_redirects)
+                .unwrap_or(http::DEFAULT_MAX_REDIRECTS),
+            proxy: args.proxy.clone().or(config.http.proxy.clone()),
+            accept_invalid_certs: args.accept_invalid_certs || config.http.accept_invalid_certs,
+            method: args.method,
+            body_bytes: if args.detect_parked || args.detect_soft_404 {
+                args.max_body_bytes
+            } else {
+                0
+            },
+        },
+        status_codes,
+        RateLimiter::new(
+            args.rate_limit,
+            args.host_rate_limit,
+            args.host_concurrency,
+            Duration::from_secs(args.max_retry_after),
+        )?,
+    )?;
+
+    // Signatures from the config file and the CLI both add to the built-in ones
+    let parked = args.detect_parked.then(|| {
+        let body = [&config.parked.body[..], &args.parked_signatures[..]].concat();
+        let nameservers = [&config.parked.nameservers[..], &args.parked_nameservers[..]].concat();
+        ParkedDetector::new(&body, &nameservers)
+    });
+    let soft_404 = args.detect_soft_404.then(|| {
+        Soft404Detector::new(&[&config.soft_404.keywords[..], &args.soft_404_keywords[..]].concat())
+    });
+
+    // Share a single DNS resolver across all tasks
+    let dns = if args.no_dns {
+        None
+    } else {
+        Some(dns::DnsChecker::new(
+            &args.dns_servers,
+            Duration::from_secs(args.dns_timeout),
+        )?)
+    };
+
+    // Share a single WHOIS server cache across all tasks
+    let whois = if args.whois {
+        Some(whois::WhoisChecker::new(
+            Duration::from_secs(args.whois_timeout),
+            args.whois_concurrency,
+        )?)
+    } else {
+        None
+    };
+
+    let suffixes = tld::SuffixList::load(args.tld_file.as_deref(), args.suffix_file.as_deref())?;
+
+    // Status cache shared by the workers, reading it, and the writer, filling it
+    let cache = match &args.cache_file {
+        Some(cache_file) => Some(Arc::new(cache::Cache::open(
+            cache_file,
+            Duration::from_secs(args.cache_active_ttl * 24 * 3600),
+            Duration::from_secs(args.cache_inactive_ttl * 3600),
+        )?)),
+        None => None,
+    };
+
+    let retest = args.retest.then(|| Retest {
+        delay: Duration::from_secs(args.retest_delay * 3600),
+        max_failures: args.retest_failures,
+    });
+
+    // Single writer task owning every output file
+    let workers_count = args.concurrency.max(1);
+    let (writer_sender, writer_receiver) = mpsc::channel::<Outcome>(workers_count * 2);
+    let writer = writer::spawn_writer(
+        WriterSettings {
+            output_file: args.output_file.clone(),
+            output_formats: args.output_format.clone(),
+            exclude: args.exclude.clone(),
+            results_file: args.results_file.clone(),
+            results_format: args.results_format,
+            checkpoint_file,
+            cache: cache.clone(),
+        },
+        writer_receiver,
+    );
+
+    let context = Arc::new(Context {
+        writer: writer_sender,
+        cache: cache.clone(),
+        retest,
+        verbose_level: args.verbose_level,
+        http,
+        probe: args.probe,
+        www_fallback: args.www_fallback,
+        cross_domain_redirect: args.cross_domain_redirect,
+        parked,
+        soft_404,
+        on_error,
+        retry,
+        dns,
+        whois,
+        suffixes,
+    });
+
+    // Fixed pool of workers pulling subjects from a bounded channel, so memory
+    // stays flat however long the input file is
+    let (sender, receiver) = mpsc::channel::<String>(workers_count * 2);
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    let workers: Vec<_> = (0..workers_count)
+        .map(|_| {
+            let receiver = receiver.clone();
+            let context = context.clone();
+            task::spawn(async move {
+                loop {
+                    let input = receiver.lock().await.recv().await;
+                    let Some(input) = input else {
+                        break;
+                    };
+                    if let Err(e) = check_domain_or_url(input, &context).await {
+                        eprintln!("Error checking domain or URL: {}", e);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    // Read input file lazily and feed the workers, stopping early on Ctrl-C so
+    // the subjects already checked still get flushed
+    let producer = async {
+        match (&args.input_file, &cache) {
+            (Some(input_file), _) => {
+                input::stream_subjects(
+                    input_file,
+                    args.input_format,
+                    &completed,
+                    sender,
+                    args.verbose_level > 1,
+                )
+                .await
+            }
+            (None, Some(cache)) => {
+                let subjects = cache.subjects_with_status(Status::Inactive)?;
+                input::send_subjects(subjects, &completed, sender).await
+            }
+            (None, None) => Err("No Input File".to_string()),
+        }
+    };
+    let produced = tokio::select! {
+        produced = producer => produced,
+        _ = signal::ctrl_c() => {
+            eprintln!("Interrupted, finishing the running checks, press Ctrl-C again to stop now...");
+            Ok(())
+        }
+    };
+
+    // Await all workers, the channel closes once the input is exhausted. A
+    // Ctrl-C meanwhile aborts the running checks, they are left out of the
+    // checkpoint and the subjects already checked still get flushed
+    let aborts: Vec<_> = workers.iter().map(|worker| worker.abort_handle()).collect();
+    let mut drained = pin!(join_all(workers));
+    let results = tokio::select! {
+        results = &mut drained => results,
+        _ = signal::ctrl_c() => {
+            eprintln!("Interrupted, stopping the running checks...");
+            aborts.iter().for_each(AbortHandle::abort);
+            drained.await
+        }
+    };
+    for result in results {
+        match result {
+            Err(e) if !e.is_cancelled() => eprintln!("Task failed: {:?}", e),
+            _ => {}
         }
     }
 
+    // Dropping the last sender lets the writer flush and stop
+    drop(context);
+    writer.await??;
+    produced?;
+
     if args.verbose_level > 0 {
         println!("All tasks completed.");
     }
@@ -0,0 +1,83 @@
+use crate::status::Status;
+use crate::syntax::SubjectKind;
+use chrono::Utc;
+use clap::ValueEnum;
+use std::net::IpAddr;
+
+/// Format of the per-status output files
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum OutputFormat {
+    /// Subjects as they were read, `<output>_<STATUS>.txt`
+    Plain,
+    /// `0.0.0.0 example.com` entries, `<output>_<STATUS>.hosts`
+    Hosts,
+    /// Adblock Plus `||example.com^` rules, `<output>_<STATUS>.abp.txt`
+    Adblock,
+    /// `address=/example.com/#` entries, `<output>_<STATUS>.dnsmasq.conf`
+    Dnsmasq,
+    /// `local-zone: "example.com." always_nxdomain` entries, `<output>_<STATUS>.unbound.conf`
+    Unbound,
+    /// Response Policy Zone, `<output>_<STATUS>.rpz`
+    Rpz,
+}
+
+impl OutputFormat {
+    /// Path of the file holding the subjects of a status in this format
+    pub fn file_path(&self, output_file: &str, status: Status) -> String {
+        let extension = match self {
+            OutputFormat::Plain => "txt",
+            OutputFormat::Hosts => "hosts",
+            OutputFormat::Adblock => "abp.txt",
+            OutputFormat::Dnsmasq => "dnsmasq.conf",
+            OutputFormat::Unbound => "unbound.conf",
+            OutputFormat::Rpz => "rpz",
+        };
+        format!("{}_{}.{}", output_file, status, extension)
+    }
+
+    /// Lines written once at the top of a new file
+    pub fn header(&self) -> Option<String> {
+        match self {
+            OutputFormat::Plain | OutputFormat::Hosts | OutputFormat::Dnsmasq => None,
+            OutputFormat::Adblock => Some("[Adblock Plus 2.0]".to_string()),
+            OutputFormat::Unbound => Some("server:".to_string()),
+            OutputFormat::Rpz => Some(format!(
+                "$TTL 300\n@ SOA localhost. root.localhost. ({} 43200 3600 604800 300)\n  NS localhost.",
+                Utc::now().format("%Y%m%d01")
+            )),
+        }
+    }
+
+    /// Format a subject, `None` when the format can't express it (e.g. an IP in a hosts
+    /// file, a URL in any DNS-level format, or an invalid subject anywhere but in a plain
+    /// file)
+    pub fn format_line(&self, subject: &str, kind: Option<SubjectKind>) -> Option<String> {
+        if *self == OutputFormat::Plain {
+            return Some(subject.to_string());
+        }
+
+        let rule = subject
+            .trim_start_matches("http://")
+            .trim_start_matches("https://");
+        match kind? {
+            SubjectKind::Ipv4Range | SubjectKind::Ipv6Range => return None,
+            // URLs keep their path, blocking the whole host would be too broad
+            SubjectKind::Url => {
+                return (*self == OutputFormat::Adblock).then(|| format!("||{}", rule));
+            }
+            _ => {}
+        }
+        let host = subject.trim_end_matches('.');
+        let is_ip = host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok();
+
+        match self {
+            OutputFormat::Plain => None,
+            OutputFormat::Adblock => Some(format!("||{}^", rule)),
+            _ if is_ip => None,
+            OutputFormat::Hosts => Some(format!("0.0.0.0 {}", host)),
+            OutputFormat::Dnsmasq => Some(format!("address=/{}/#", host)),
+            OutputFormat::Unbound => Some(format!("  local-zone: \"{}.\" always_nxdomain", host)),
+            OutputFormat::Rpz => Some(format!("{0} CNAME .\n*.{0} CNAME .", host)),
+        }
+    }
+}
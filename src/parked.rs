@@ -0,0 +1,79 @@
+// Phrases of parking pages, sale listings and registrar placeholder pages,
+// brand names alone also appear on the many sites linking to their services
+const BODY_SIGNATURES: [&str; 12] = [
+    "domain is for sale",
+    "domain may be for sale",
+    "buy this domain",
+    "this domain is parked",
+    "parked free",
+    "domain has expired",
+    "this domain has been registered",
+    "future home of something quite cool",
+    "website coming soon",
+    "this site is under construction",
+    "registered at namecheap.com",
+    "is registered with godaddy",
+];
+
+// Domains of the name servers of parking services
+const NAMESERVER_SIGNATURES: [&str; 9] = [
+    "sedoparking.com",
+    "parkingcrew.net",
+    "bodis.com",
+    "above.com",
+    "dan.com",
+    "afternic.com",
+    "parklogic.com",
+    "uniregistrymarket.link",
+    "hugedomains.com",
+];
+
+/// Content and name server signatures of parked and placeholder domains
+pub struct ParkedDetector {
+    body: Vec<String>,
+    nameservers: Vec<String>,
+}
+
+impl ParkedDetector {
+    /// Detector for the built-in signatures and the given page phrases and
+    /// name server domains, in any case
+    pub fn new(extra_body: &[String], extra_nameservers: &[String]) -> Self {
+        let lowercase = |signatures: &[String]| -> Vec<String> {
+            signatures.iter().map(|s| s.to_lowercase()).collect()
+        };
+        let body = BODY_SIGNATURES
+            .iter()
+            .map(|s| s.to_string())
+            .chain(lowercase(extra_body))
+            .collect();
+        let nameservers = NAMESERVER_SIGNATURES
+            .iter()
+            .map(|s| s.to_string())
+            .chain(lowercase(extra_nameservers))
+            .collect();
+
+        ParkedDetector { body, nameservers }
+    }
+
+    /// Signature found in the beginning of a page
+    pub fn match_body(&self, body: &str) -> Option<&str> {
+        let body = body.to_lowercase();
+        self.body
+            .iter()
+            .find(|signature| body.contains(signature.as_str()))
+            .map(String::as_str)
+    }
+
+    /// Signature matching the domain of one of the name servers of a domain
+    pub fn match_nameservers(&self, nameservers: &[String]) -> Option<&str> {
+        nameservers.iter().find_map(|nameserver| {
+            let nameserver = nameserver.trim_end_matches('.').to_lowercase();
+            self.nameservers
+                .iter()
+                .find(|signature| {
+                    nameserver == **signature || nameserver.ends_with(&format!(".{}", signature))
+                })
+                .map(String::as_str)
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use crate::syntax::SubjectKind;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Schemes a subject without one is probed over
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum ProbeStrategy {
+    /// HTTPS, then HTTP when it fails
+    HttpsFirst,
+    /// HTTP, then HTTPS when it fails
+    HttpFirst,
+    /// HTTPS and HTTP at the same time, the best answer wins
+    Both,
+    /// HTTPS only
+    HttpsOnly,
+}
+
+/// Host variant of a domain a probe went to
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    /// The registrable domain itself, `example.com`
+    Apex,
+    /// Its `www.` subdomain, `www.example.com`
+    Www,
+}
+
+impl Variant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Variant::Apex => "apex",
+            Variant::Www => "www",
+        }
+    }
+}
+
+/// URL to probe, with the host variant it goes to when the subject is a domain
+pub struct Target {
+    pub url: String,
+    pub variant: Option<Variant>,
+}
+
+impl Target {
+    pub fn scheme(&self) -> Option<&str> {
+        self.url.split_once("://").map(|(scheme, _)| scheme)
+    }
+}
+
+impl ProbeStrategy {
+    /// Schemes in the order they are tried
+    fn schemes(&self) -> &'static [&'static str] {
+        match self {
+            ProbeStrategy::HttpsFirst | ProbeStrategy::Both => &["https", "http"],
+            ProbeStrategy::HttpFirst => &["http", "https"],
+            ProbeStrategy::HttpsOnly => &["https"],
+        }
+    }
+
+    /// URLs to probe for a subject, a URL with a scheme is probed as is. With
+    /// `www_fallback`, a domain is then probed on its `www.` subdomain and a
+    /// `www.` subdomain on its apex
+    pub fn targets(&self, input: &str, kind: SubjectKind, www_fallback: bool) -> Vec<Target> {
+        if input.starts_with("http://") || input.starts_with("https://") {
+            return vec![Target {
+                url: input.to_string(),
+                variant: None,
+            }];
+        }
+
+        let www = input.strip_prefix("www.");
+        let hosts = match kind {
+            SubjectKind::Ipv6 => vec![(format!("[{}]", input), None)],
+            SubjectKind::Domain if www_fallback => vec![
+                (input.to_string(), Some(Variant::Apex)),
+                (format!("www.{}", input), Some(Variant::Www)),
+            ],
+            SubjectKind::Domain => vec![(input.to_string(), Some(Variant::Apex))],
+            SubjectKind::Subdomain if www.is_some() => {
+                let mut hosts = vec![(input.to_string(), Some(Variant::Www))];
+                if www_fallback {
+                    hosts.extend(www.map(|apex| (apex.to_string(), Some(Variant::Apex))));
+                }
+                hosts
+            }
+            _ => vec![(input.to_string(), None)],
+        };
+
+        hosts
+            .iter()
+            .flat_map(|(host, variant)| {
+                self.schemes().iter().map(move |scheme| Target {
+                    url: format!("{}://{}", scheme, host),
+                    variant: *variant,
+                })
+            })
+            .collect()
+    }
+}
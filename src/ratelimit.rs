@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// Hosts tracked before the idle ones are forgotten
+const MAX_TRACKED_HOSTS: usize = 10_000;
+
+/// Token bucket refilled at `rate` tokens per second, holding up to one
+/// second worth of tokens
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        let capacity = rate.max(1.0);
+        TokenBucket {
+            rate,
+            capacity,
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Take a token, returning how long to wait before using it, tokens are
+    /// borrowed from the future so waiting callers are served in order
+    fn take(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+    }
+}
+
+/// Politeness state of a single host
+struct Host {
+    bucket: Option<TokenBucket>,
+    slots: Option<Arc<Semaphore>>,
+    blocked_until: Option<Instant>,
+}
+
+/// Global and per-host request rates, per-host concurrency caps and the
+/// pauses asked by servers through `Retry-After`
+pub struct RateLimiter {
+    global: Option<Mutex<TokenBucket>>,
+    host_rate: Option<f64>,
+    host_concurrency: Option<usize>,
+    hosts: Mutex<HashMap<String, Host>>,
+    pub max_retry_after: Duration,
+}
+
+impl RateLimiter {
+    /// Rates are in requests per second, `None` leaves them unlimited
+    pub fn new(
+        global_rate: Option<f64>,
+        host_rate: Option<f64>,
+        host_concurrency: Option<usize>,
+        max_retry_after: Duration,
+    ) -> Result<Self, String> {
+        for rate in global_rate.iter().chain(host_rate.iter()) {
+            if !rate.is_finite() || *rate <= 0.0 {
+                return Err(format!("Invalid Rate Limit: {}", rate));
+            }
+        }
+        if host_concurrency == Some(0) {
+            return Err("Invalid Host Concurrency: 0".to_string());
+        }
+
+        Ok(RateLimiter {
+            global: global_rate.map(|rate| Mutex::new(TokenBucket::new(rate))),
+            host_rate,
+            host_concurrency,
+            hosts: Mutex::new(HashMap::new()),
+            max_retry_after,
+        })
+    }
+
+    /// Wait until a request to the host is allowed, the returned permit holds
+    /// one of its concurrency slots until dropped
+    pub async fn acquire(&self, host: &str) -> Option<OwnedSemaphorePermit> {
+        let now = Instant::now();
+        let (host_wait, slots) = {
+            let mut hosts = self.hosts.lock().unwrap();
+            if hosts.len() > MAX_TRACKED_HOSTS {
+                hosts.retain(|_, host| !self.is_idle(host, now));
+            }
+
+            let state = hosts.entry(host.to_string()).or_insert_with(|| Host {
+                bucket: self.host_rate.map(TokenBucket::new),
+                slots: self
+                    .host_concurrency
+                    .map(|slots| Arc::new(Semaphore::new(slots))),
+                blocked_until: None,
+            });
+            let rate_wait = state
+                .bucket
+                .as_mut()
+                .map_or(Duration::ZERO, |bucket| bucket.take(now));
+            let blocked_wait = state
+                .blocked_until
+                .map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+            (rate_wait.max(blocked_wait), state.slots.clone())
+        };
+        let global_wait = self
+            .global
+            .as_ref()
+            .map_or(Duration::ZERO, |bucket| bucket.lock().unwrap().take(now));
+
+        tokio::time::sleep(host_wait.max(global_wait)).await;
+        match slots {
+            Some(slots) => slots.acquire_owned().await.ok(),
+            None => None,
+        }
+    }
+
+    /// Hold every request to the host for the given delay, as asked by a
+    /// `Retry-After` header, at most for `max_retry_after`
+    pub fn block(&self, host: &str, delay: Duration) {
+        let until = Instant::now() + delay.min(self.max_retry_after);
+        if let Some(state) = self.hosts.lock().unwrap().get_mut(host) {
+            state.blocked_until = Some(state.blocked_until.map_or(until, |u| u.max(until)));
+        }
+    }
+
+    /// Whether forgetting the host changes nothing
+    fn is_idle(&self, host: &Host, now: Instant) -> bool {
+        let unblocked = host.blocked_until.is_none_or(|until| until <= now);
+        let no_request = match (&host.slots, self.host_concurrency) {
+            (Some(slots), Some(total)) => slots.available_permits() == total,
+            _ => true,
+        };
+        let refilled = host.bucket.as_ref().is_none_or(|bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens + elapsed * bucket.rate >= bucket.capacity
+        });
+        unblocked && no_request && refilled
+    }
+}
@@ -0,0 +1,163 @@
+use crate::errors::ErrorKind;
+use crate::http::Hop;
+use crate::probe::Variant;
+use crate::retry::Attempts;
+use crate::status::Status;
+use clap::ValueEnum;
+use serde::Serialize;
+
+// Columns of the CSV results file, in the order of `Record::to_csv`
+const CSV_HEADER: &str =
+    "subject,status,source,scheme,variant,http_status_code,final_url,redirects,ips,total_ms,whois_ms,dns_ms,http_ms,\
+     whois_attempts,dns_attempts,http_attempts,signature,error_kind,error";
+
+/// Format of the structured results file
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum ResultsFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// Comma separated values with a header line
+    Csv,
+}
+
+impl ResultsFormat {
+    /// Line written once at the top of a new file
+    pub fn header(&self) -> Option<String> {
+        match self {
+            ResultsFormat::Jsonl => None,
+            ResultsFormat::Csv => Some(CSV_HEADER.to_string()),
+        }
+    }
+}
+
+/// Check that decided the status of a subject
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum StatusSource {
+    Http,
+    Dns,
+    Whois,
+    Syntax,
+    Cache,
+}
+
+impl StatusSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatusSource::Http => "HTTP",
+            StatusSource::Dns => "DNS",
+            StatusSource::Whois => "WHOIS",
+            StatusSource::Syntax => "SYNTAX",
+            StatusSource::Cache => "CACHE",
+        }
+    }
+}
+
+/// Time spent in each stage, in milliseconds
+#[derive(Default, Serialize)]
+pub struct Timings {
+    pub total_ms: u64,
+    pub whois_ms: Option<u64>,
+    pub dns_ms: Option<u64>,
+    pub http_ms: Option<u64>,
+}
+
+/// Everything learned about a subject while checking it
+#[derive(Serialize)]
+pub struct Record {
+    pub subject: String,
+    pub status: Status,
+    pub source: StatusSource,
+    pub scheme: Option<String>,
+    pub variant: Option<Variant>,
+    pub http_status_code: Option<u16>,
+    pub final_url: Option<String>,
+    pub redirects: Vec<Hop>,
+    pub ips: Vec<String>,
+    pub timings: Timings,
+    pub attempts: Attempts,
+    /// Content or name server signature behind a PARKED or soft-404 status
+    pub signature: Option<String>,
+    pub error_kind: Option<ErrorKind>,
+    pub error: Option<String>,
+}
+
+impl Record {
+    pub fn new(subject: &str) -> Self {
+        Record {
+            subject: subject.to_string(),
+            status: Status::Invalid,
+            source: StatusSource::Syntax,
+            scheme: None,
+            variant: None,
+            http_status_code: None,
+            final_url: None,
+            redirects: vec![],
+            ips: vec![],
+            timings: Timings::default(),
+            attempts: Attempts::default(),
+            signature: None,
+            error_kind: None,
+            error: None,
+        }
+    }
+
+    /// Serialize the record as a single line of the given format
+    pub fn to_line(&self, format: ResultsFormat) -> Result<String, String> {
+        match format {
+            ResultsFormat::Jsonl => serde_json::to_string(self).map_err(|e| e.to_string()),
+            ResultsFormat::Csv => Ok(self.to_csv()),
+        }
+    }
+
+    /// Single CSV row matching `CSV_HEADER`, IPs are separated by spaces and
+    /// redirects written as `<code> <url>` separated by ` > `
+    fn to_csv(&self) -> String {
+        let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        let attempts = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+        [
+            csv_field(&self.subject),
+            self.status.to_string(),
+            self.source.as_str().to_string(),
+            self.scheme.clone().unwrap_or_default(),
+            self.variant
+                .map(|variant| variant.as_str().to_string())
+                .unwrap_or_default(),
+            self.http_status_code
+                .map(|code| code.to_string())
+                .unwrap_or_default(),
+            csv_field(self.final_url.as_deref().unwrap_or_default()),
+            csv_field(
+                &self
+                    .redirects
+                    .iter()
+                    .map(|hop| format!("{} {}", hop.status_code, hop.url))
+                    .collect::<Vec<_>>()
+                    .join(" > "),
+            ),
+            csv_field(&self.ips.join(" ")),
+            self.timings.total_ms.to_string(),
+            optional(self.timings.whois_ms),
+            optional(self.timings.dns_ms),
+            optional(self.timings.http_ms),
+            attempts(self.attempts.whois),
+            attempts(self.attempts.dns),
+            attempts(self.attempts.http),
+            csv_field(self.signature.as_deref().unwrap_or_default()),
+            self.error_kind
+                .map(|kind| kind.to_string())
+                .unwrap_or_default(),
+            csv_field(self.error.as_deref().unwrap_or_default()),
+        ]
+        .join(",")
+    }
+}
+
+/// Quote a CSV field when it contains a separator, a quote or a line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,61 @@
+use crate::errors::ErrorKind;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
+
+/// Number of attempts each check needed, `None` when the check did not run
+#[derive(Default, Serialize)]
+pub struct Attempts {
+    pub whois: Option<u32>,
+    pub dns: Option<u32>,
+    pub http: Option<u32>,
+}
+
+/// How failed checks are retried: up to `retries` more attempts for the
+/// retryable kinds of error, waiting an exponential backoff between attempts
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    /// Fraction of each delay that is randomized, from 0 to 1
+    pub jitter: f64,
+    pub retryable: HashSet<ErrorKind>,
+}
+
+impl RetryPolicy {
+    /// Run a check until it succeeds, fails with an error that is not
+    /// retryable or runs out of attempts, returning its last result and the
+    /// number of attempts
+    pub async fn run<T, F, Fut>(
+        &self,
+        mut check: F,
+        error_kind: impl Fn(&T) -> Option<ErrorKind>,
+    ) -> (T, u32)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let mut attempt = 1;
+        loop {
+            let result = check().await;
+            match error_kind(&result) {
+                Some(kind) if attempt <= self.retries && self.retryable.contains(&kind) => {
+                    tokio::time::sleep(self.delay(attempt)).await;
+                    attempt += 1;
+                }
+                _ => return (result, attempt),
+            }
+        }
+    }
+
+    /// Delay after the given failed attempt, doubling each time up to
+    /// `max_backoff`, minus a random share so retries don't move in lockstep
+    fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff);
+        exponential.mul_f64(1.0 - self.jitter * rand::random::<f64>())
+    }
+}
@@ -0,0 +1,17 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Value looked up once and shared, tasks asking while the lookup runs wait
+/// for it, a failed lookup is left for the next task to try again
+pub type Shared<T> = Arc<OnceCell<T>>;
+
+/// Cell of a key, created empty on first use
+pub fn shared<T>(cells: &Mutex<HashMap<String, Shared<T>>>, key: &str) -> Shared<T> {
+    cells
+        .lock()
+        .unwrap()
+        .entry(key.to_string())
+        .or_default()
+        .clone()
+}
@@ -0,0 +1,223 @@
+use crate::http::{HttpChecker, HttpOutcome};
+use crate::shared::{shared, Shared};
+use reqwest::Url;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+// Phrases of "not found" pages served with a success status
+const KEYWORDS: [&str; 12] = [
+    "page not found",
+    "404 not found",
+    "error 404",
+    "404 error",
+    "page doesn't exist",
+    "page does not exist",
+    "page could not be found",
+    "page cannot be found",
+    "page you requested could not be found",
+    "nothing was found at this location",
+    "this page is no longer available",
+    "the requested url was not found",
+];
+
+// Share of words a page must have in common with the page of a nonexistent
+// path to be considered the same page
+const SIMILARITY_THRESHOLD: f64 = 0.9;
+
+/// Detector of pages answering with a success status where a 404 is due
+pub struct Soft404Detector {
+    keywords: Vec<String>,
+    /// Page served for a random path of each origin, `None` when the origin
+    /// answers such paths with a proper error
+    baselines: Mutex<HashMap<String, Shared<Option<String>>>>,
+}
+
+impl Soft404Detector {
+    /// Detector looking for the built-in phrases and the given ones, in any case
+    pub fn new(extra_keywords: &[String]) -> Self {
+        let keywords = KEYWORDS
+            .iter()
+            .map(|keyword| keyword.to_string())
+            .chain(extra_keywords.iter().map(|keyword| keyword.to_lowercase()))
+            .collect();
+
+        Soft404Detector {
+            keywords,
+            baselines: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Why the page a URL landed on is a soft 404, `None` when it looks genuine.
+    /// Keywords are only looked for in the title and the main heading, and root
+    /// pages are not compared with a random path, sites redirecting unknown
+    /// paths to their home page would serve the same page for both
+    pub async fn check(
+        &self,
+        probed_url: &str,
+        url: &str,
+        body: &str,
+        http: &HttpChecker,
+        verbose: bool,
+    ) -> Option<String> {
+        if let Some(keyword) = self.match_headings(body) {
+            return Some(format!("soft-404 keyword: {}", keyword));
+        }
+
+        let root = Url::parse(probed_url).map_or(true, |probed| probed.path() == "/");
+        if root {
+            return None;
+        }
+
+        let url = Url::parse(url).ok()?;
+        let baseline = self.baseline(&url, http, verbose).await?;
+        let path = url.path().trim_matches('/');
+        let similarity = similarity(&remove(body, path), &baseline);
+        if verbose {
+            println!("Soft-404 similarity of {}: {:.2}", url, similarity);
+        }
+
+        (similarity >= SIMILARITY_THRESHOLD).then(|| "soft-404 random path".to_string())
+    }
+
+    /// Keyword found in the title or a main heading of a page
+    fn match_headings(&self, body: &str) -> Option<&str> {
+        let headings = headings(body);
+        self.keywords
+            .iter()
+            .find(|keyword| headings.contains(keyword.as_str()))
+            .map(String::as_str)
+    }
+
+    /// Page served for a random path of the origin of a URL, fetched once per
+    /// origin by the first task asking, a failed fetch is tried again later
+    async fn baseline(&self, url: &Url, http: &HttpChecker, verbose: bool) -> Option<String> {
+        let origin = url.origin().ascii_serialization();
+        let baseline = shared(&self.baselines, &origin);
+        let baseline = baseline
+            .get_or_try_init(|| async {
+                let token = format!(
+                    "{:016x}{:016x}",
+                    rand::random::<u64>(),
+                    rand::random::<u64>()
+                );
+                let random_url = format!("{}/{}", origin, token);
+                let result = http.check_http(&random_url, verbose).await;
+                match (result.outcome, result.body) {
+                    (HttpOutcome::Error(..), _) => Err(()),
+                    (HttpOutcome::Active, Some(body)) => Ok(Some(remove(&body, &token))),
+                    _ => Ok(None),
+                }
+            })
+            .await;
+        baseline.ok()?.clone()
+    }
+}
+
+/// Lowercase text of the `<title>` and `<h1>` elements of a page, where "not
+/// found" pages say so, unlike the scripts, menus and articles of real pages
+fn headings(body: &str) -> String {
+    let body = body.to_lowercase();
+    let mut text = String::new();
+    for tag in ["title", "h1"] {
+        let open = format!("<{}", tag);
+        let close = format!("</{}", tag);
+        let mut rest = body.as_str();
+        while let Some(start) = rest.find(&open) {
+            rest = &rest[start + open.len()..];
+            // `<h1>` or `<h1 class=...>`, not `<h10>` or `<titles>`
+            if !rest.starts_with(['>', ' ', '\t', '\n', '\r']) {
+                continue;
+            }
+            let Some(content) = rest.find('>').map(|end| &rest[end + 1..]) else {
+                break;
+            };
+            let end = content.find(&close).unwrap_or(content.len());
+            text.push_str(&strip_tags(&content[..end]));
+            text.push('\n');
+            rest = &content[end..];
+        }
+    }
+    text
+}
+
+/// Text without its markup
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Body without the occurrences of the path it was served for, pages often
+/// echo it
+fn remove(body: &str, path: &str) -> String {
+    if path.is_empty() {
+        body.to_string()
+    } else {
+        body.replace(path, "")
+    }
+}
+
+/// Jaccard similarity of the words of two pages, from 0 to 1, two pages without
+/// words say nothing and score 0
+fn similarity(a: &str, b: &str) -> f64 {
+    let words = |text: &str| -> HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similarity_of_words() {
+        assert_eq!(similarity("Oops, nothing here", "oops nothing HERE!"), 1.0);
+        assert_eq!(similarity("a b c d", "a b e f"), 2.0 / 6.0);
+        assert_eq!(similarity("welcome", "goodbye"), 0.0);
+        assert_eq!(similarity("", ""), 0.0);
+    }
+
+    #[test]
+    fn remove_echoed_path() {
+        assert_eq!(
+            remove("<p>/blog/post was not found</p>", "blog/post"),
+            "<p>/ was not found</p>"
+        );
+        assert_eq!(remove("<p>home</p>", ""), "<p>home</p>");
+    }
+
+    #[test]
+    fn keywords_in_headings_only() {
+        let detector = Soft404Detector::new(&["Gone Fishing".to_string()]);
+
+        let title = "<html><head><title>404 Not Found</title></head><body></body></html>";
+        assert_eq!(detector.match_headings(title), Some("404 not found"));
+
+        let heading = "<h1 class=\"big\">Sorry, <em>Page Not Found</em></h1>";
+        assert_eq!(detector.match_headings(heading), Some("page not found"));
+
+        let extra = "<TITLE>Gone fishing</TITLE>";
+        assert_eq!(detector.match_headings(extra), Some("gone fishing"));
+
+        let body = "<title>Blog</title><h10>page not found</h10>\
+                    <p>How we handle page not found errors</p>\
+                    <script>showError('404 not found')</script>";
+        assert_eq!(detector.match_headings(body), None);
+    }
+}
@@ -0,0 +1,71 @@
+use colored::*;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Final status of a subject, each one has its own output file
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Status {
+    Active,
+    Inactive,
+    Invalid,
+    Dead,
+    Unknown,
+    Parked,
+    Redirected,
+}
+
+impl Status {
+    pub const ALL: [Status; 7] = [
+        Status::Active,
+        Status::Inactive,
+        Status::Invalid,
+        Status::Dead,
+        Status::Unknown,
+        Status::Parked,
+        Status::Redirected,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Active => "ACTIVE",
+            Status::Inactive => "INACTIVE",
+            Status::Invalid => "INVALID",
+            Status::Dead => "DEAD",
+            Status::Unknown => "UNKNOWN",
+            Status::Parked => "PARKED",
+            Status::Redirected => "REDIRECTED",
+        }
+    }
+
+    /// Status name colored for the terminal
+    pub fn colored(&self) -> ColoredString {
+        match self {
+            Status::Active => self.as_str().bold().green(),
+            Status::Inactive => self.as_str().bold().red(),
+            Status::Invalid => self.as_str().bold().yellow(),
+            Status::Dead => self.as_str().bold().magenta(),
+            Status::Unknown => self.as_str().bold().blue(),
+            Status::Parked => self.as_str().bold().cyan(),
+            Status::Redirected => self.as_str().bold().white(),
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Status::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+            .ok_or_else(|| format!("Unknown Status: {}", value))
+    }
+}
@@ -0,0 +1,194 @@
+use crate::tld::SuffixList;
+use reqwest::Url;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+// Maximum length of a full domain name, without the trailing dot
+const MAX_DOMAIN_LENGTH: usize = 253;
+
+// Maximum length of a single label
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// Kind of a syntactically valid subject
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SubjectKind {
+    Domain,
+    Subdomain,
+    Ipv4,
+    Ipv6,
+    Ipv4Range,
+    Ipv6Range,
+    Url,
+}
+
+impl SubjectKind {
+    /// Whether the subject is a domain name that can be looked up in DNS and WHOIS
+    pub fn is_domain(&self) -> bool {
+        matches!(self, SubjectKind::Domain | SubjectKind::Subdomain)
+    }
+
+    /// Whether the subject is an IP range that can't be probed as a single host
+    pub fn is_range(&self) -> bool {
+        matches!(self, SubjectKind::Ipv4Range | SubjectKind::Ipv6Range)
+    }
+}
+
+/// Classify a subject, returning `None` when its syntax is invalid or its TLD unknown
+pub fn classify(input: &str, suffixes: &SuffixList) -> Option<SubjectKind> {
+    if input.parse::<Ipv4Addr>().is_ok() {
+        return Some(SubjectKind::Ipv4);
+    }
+    if input.parse::<Ipv6Addr>().is_ok() {
+        return Some(SubjectKind::Ipv6);
+    }
+    // An address with a prefix is a range or nothing, never an URL path
+    if let Some((address, _)) = input.split_once('/') {
+        if address.parse::<IpAddr>().is_ok() {
+            return classify_range(input);
+        }
+    }
+    if input.starts_with("http://") || input.starts_with("https://") || input.contains('/') {
+        return is_valid_url(input, suffixes).then_some(SubjectKind::Url);
+    }
+
+    // Internationalized names are checked in their punycode form
+    let ascii;
+    let input = if input.is_ascii() {
+        input
+    } else {
+        ascii = Url::parse(&format!("http://{}", input)).ok()?;
+        ascii.host_str()?
+    };
+
+    if !is_valid_domain(input, suffixes) {
+        return None;
+    }
+
+    // Public suffixes like `github.io` are hosts on their own
+    match suffixes.registrable_domain(input) {
+        Some(domain) if domain != input.trim_end_matches('.').to_lowercase() => {
+            Some(SubjectKind::Subdomain)
+        }
+        _ => Some(SubjectKind::Domain),
+    }
+}
+
+/// Classify an IP range in CIDR notation
+fn classify_range(input: &str) -> Option<SubjectKind> {
+    let (address, prefix) = input.split_once('/')?;
+    let prefix = prefix.parse::<u8>().ok()?;
+
+    match address.parse::<IpAddr>().ok()? {
+        IpAddr::V4(_) if prefix <= 32 => Some(SubjectKind::Ipv4Range),
+        IpAddr::V6(_) if prefix <= 128 => Some(SubjectKind::Ipv6Range),
+        _ => None,
+    }
+}
+
+/// Check an URL, bare URLs like `example.com/page` are assumed to be HTTP
+fn is_valid_url(input: &str, suffixes: &SuffixList) -> bool {
+    let url = if input.starts_with("http://") || input.starts_with("https://") {
+        Url::parse(input)
+    } else {
+        Url::parse(&format!("http://{}", input))
+    };
+
+    let Ok(url) = url else {
+        return false;
+    };
+
+    // The parser already validated bracketed IPv6 hosts
+    match url.host_str() {
+        Some(host) if host.starts_with('[') => true,
+        Some(host) => host.parse::<Ipv4Addr>().is_ok() || is_valid_domain(host, suffixes),
+        None => false,
+    }
+}
+
+/// Validate a domain name, its TLD must be delegated in the root zone
+fn is_valid_domain(input: &str, suffixes: &SuffixList) -> bool {
+    let domain = input.strip_suffix('.').unwrap_or(input);
+    if domain.is_empty() || domain.len() > MAX_DOMAIN_LENGTH {
+        return false;
+    }
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    let Some((tld, rest)) = labels.split_last() else {
+        return false;
+    };
+
+    !rest.is_empty()
+        && is_valid_tld(tld)
+        && suffixes.is_known_tld(tld)
+        && rest.iter().all(|label| is_valid_label(label))
+}
+
+/// Check a single label, underscores are tolerated as some records use them
+fn is_valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= MAX_LABEL_LENGTH
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Check the top-level label, which is alphabetic or punycode
+fn is_valid_tld(tld: &str) -> bool {
+    let tld = tld.to_ascii_lowercase();
+    if let Some(punycode) = tld.strip_prefix("xn--") {
+        return is_valid_label(punycode) && !punycode.contains('_');
+    }
+    tld.len() >= 2 && tld.len() <= MAX_LABEL_LENGTH && tld.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(input: &str) -> Option<SubjectKind> {
+        classify(input, &SuffixList::load(None, None).unwrap())
+    }
+
+    #[test]
+    fn classify_domains() {
+        assert_eq!(kind("example.com"), Some(SubjectKind::Domain));
+        assert_eq!(kind("example.com."), Some(SubjectKind::Domain));
+        assert_eq!(kind("www.example.com"), Some(SubjectKind::Subdomain));
+        assert_eq!(kind("a.b.example.co.uk"), Some(SubjectKind::Subdomain));
+        assert_eq!(kind("example.github.io"), Some(SubjectKind::Domain));
+        assert_eq!(kind("bücher.de"), Some(SubjectKind::Domain));
+        assert_eq!(kind("xn--bcher-kva.de"), Some(SubjectKind::Domain));
+        assert_eq!(kind("#.com"), None);
+        assert_eq!(kind("-example.com"), None);
+        assert_eq!(kind("example.ywh"), None);
+        assert_eq!(kind("com"), None);
+    }
+
+    #[test]
+    fn classify_addresses() {
+        assert_eq!(kind("192.0.2.1"), Some(SubjectKind::Ipv4));
+        assert_eq!(kind("2001:db8::1"), Some(SubjectKind::Ipv6));
+        assert_eq!(kind("192.0.2.0/24"), Some(SubjectKind::Ipv4Range));
+        assert_eq!(kind("2001:db8::/32"), Some(SubjectKind::Ipv6Range));
+        assert_eq!(kind("1.2.3.4/33"), None);
+        assert_eq!(kind("2001:db8::/129"), None);
+        assert_eq!(kind("1.2.3.4/abc"), None);
+        assert_eq!(kind("1.2.3.4/24/page"), None);
+        assert_eq!(kind("999.2.3.4"), None);
+    }
+
+    #[test]
+    fn classify_urls() {
+        assert_eq!(kind("https://example.com"), Some(SubjectKind::Url));
+        assert_eq!(
+            kind("http://www.example.com/page?q=1"),
+            Some(SubjectKind::Url)
+        );
+        assert_eq!(kind("http://192.0.2.1:8080/"), Some(SubjectKind::Url));
+        assert_eq!(kind("http://[2001:db8::1]/"), Some(SubjectKind::Url));
+        assert_eq!(kind("example.com/page"), Some(SubjectKind::Url));
+        assert_eq!(kind("https://example.ywh/"), None);
+        assert_eq!(kind("https://#.com/"), None);
+    }
+}
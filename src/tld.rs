@@ -0,0 +1,221 @@
+use reqwest::Url;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+// Lists embedded at build time, can be refreshed from local files. The TLDs are
+// the top-level rules of the ICANN section of the Public Suffix List, in the
+// format of IANA's `tlds-alpha-by-domain.txt`
+const EMBEDDED_TLDS: &str = include_str!("../data/psl_tlds.txt");
+const EMBEDDED_SUFFIXES: &str = include_str!("../data/public_suffix_list.dat");
+
+/// Section of the Public Suffix List a rule comes from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Section {
+    Icann,
+    Private,
+}
+
+/// Public suffix of a domain
+pub struct Suffix {
+    pub labels: usize,
+    pub section: Section,
+}
+
+/// Root-zone TLDs and Public Suffix List rules
+pub struct SuffixList {
+    tlds: HashSet<String>,
+    rules: HashMap<String, Section>,
+    wildcards: HashMap<String, Section>,
+    exceptions: HashSet<String>,
+}
+
+impl SuffixList {
+    /// Load the lists from local files, falling back to the embedded ones
+    pub fn load(tld_file: Option<&str>, suffix_file: Option<&str>) -> Result<Self, String> {
+        let tlds = match tld_file {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("TLD List Read Failed: {}: {}", path, e))?,
+            None => EMBEDDED_TLDS.to_string(),
+        };
+        let suffixes = match suffix_file {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("Public Suffix List Read Failed: {}: {}", path, e))?,
+            None => EMBEDDED_SUFFIXES.to_string(),
+        };
+
+        Ok(SuffixList::parse(&tlds, &suffixes))
+    }
+
+    /// Parse a TLD list in IANA's format and the Public Suffix List
+    fn parse(tlds: &str, suffixes: &str) -> Self {
+        let mut list = SuffixList {
+            tlds: tlds
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_lowercase)
+                .collect(),
+            rules: HashMap::new(),
+            wildcards: HashMap::new(),
+            exceptions: HashSet::new(),
+        };
+
+        let mut section = Section::Icann;
+        for line in suffixes.lines().map(str::trim) {
+            if line.starts_with("// ===BEGIN PRIVATE DOMAINS===") {
+                section = Section::Private;
+            }
+            // Rules end at the first whitespace, the rest is a comment
+            let rule = line.split_whitespace().next().unwrap_or_default();
+            if rule.is_empty() || rule.starts_with("//") {
+                continue;
+            }
+
+            if let Some(exception) = rule.strip_prefix('!') {
+                list.exceptions.insert(to_ascii(exception));
+            } else if let Some(parent) = rule.strip_prefix("*.") {
+                list.wildcards.insert(to_ascii(parent), section);
+            } else {
+                list.rules.insert(to_ascii(rule), section);
+            }
+        }
+
+        list
+    }
+
+    /// Whether the TLD is delegated in the root zone
+    pub fn is_known_tld(&self, tld: &str) -> bool {
+        self.tlds.contains(&tld.to_lowercase())
+    }
+
+    /// Find the public suffix of a domain, unknown TLDs default to a single label
+    pub fn public_suffix(&self, domain: &str) -> Suffix {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let labels: Vec<&str> = domain.split('.').collect();
+
+        // The longest matching rule wins, exceptions always beat their wildcard
+        for start in 0..labels.len() {
+            let candidate = labels[start..].join(".");
+            if self.exceptions.contains(&candidate) {
+                let section = self
+                    .wildcards
+                    .get(&labels[start + 1..].join("."))
+                    .copied()
+                    .unwrap_or(Section::Icann);
+                return Suffix {
+                    labels: labels.len() - start - 1,
+                    section,
+                };
+            }
+
+            let rule = self.rules.get(&candidate).or_else(|| {
+                labels
+                    .get(start + 1..)
+                    .filter(|parent| !parent.is_empty())
+                    .and_then(|parent| self.wildcards.get(&parent.join(".")))
+            });
+            if let Some(section) = rule {
+                return Suffix {
+                    labels: labels.len() - start,
+                    section: *section,
+                };
+            }
+        }
+
+        Suffix {
+            labels: 1,
+            section: Section::Icann,
+        }
+    }
+
+    /// Registrable domain (eTLD+1), `None` when the domain is a public suffix itself
+    pub fn registrable_domain(&self, domain: &str) -> Option<String> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let labels: Vec<&str> = domain.split('.').collect();
+        let suffix = self.public_suffix(&domain);
+
+        (labels.len() > suffix.labels).then(|| labels[labels.len() - suffix.labels - 1..].join("."))
+    }
+
+    /// Registrable domain of a host, the host itself for IP addresses and
+    /// public suffixes
+    pub fn site(&self, host: &str) -> String {
+        if host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok() {
+            return host.to_string();
+        }
+        self.registrable_domain(host)
+            .unwrap_or_else(|| host.trim_end_matches('.').to_lowercase())
+    }
+}
+
+/// Convert internationalized rules to punycode, like the subjects they match
+fn to_ascii(rule: &str) -> String {
+    if rule.is_ascii() {
+        return rule.to_lowercase();
+    }
+    Url::parse(&format!("http://{}", rule))
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| rule.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUFFIXES: &str = "// ===BEGIN ICANN DOMAINS===
+com
+uk
+co.uk
+jp
+*.kawasaki.jp
+!city.kawasaki.jp
+// ===BEGIN PRIVATE DOMAINS===
+github.io
+*.compute.example
+";
+
+    fn list() -> SuffixList {
+        SuffixList::parse("# Version 1\nCOM\nUK\nJP\nIO\n", SUFFIXES)
+    }
+
+    fn suffix(domain: &str) -> (usize, Section) {
+        let suffix = list().public_suffix(domain);
+        (suffix.labels, suffix.section)
+    }
+
+    #[test]
+    fn public_suffix_rules() {
+        assert_eq!(suffix("example.com"), (1, Section::Icann));
+        assert_eq!(suffix("www.example.co.uk."), (2, Section::Icann));
+        assert_eq!(suffix("user.github.io"), (2, Section::Private));
+        assert_eq!(suffix("example.unknown"), (1, Section::Icann));
+    }
+
+    #[test]
+    fn public_suffix_wildcards_and_exceptions() {
+        assert_eq!(suffix("www.shop.kawasaki.jp"), (3, Section::Icann));
+        assert_eq!(suffix("www.city.kawasaki.jp"), (2, Section::Icann));
+        assert_eq!(suffix("a.b.compute.example"), (3, Section::Private));
+    }
+
+    #[test]
+    fn registrable_domains() {
+        let list = list();
+        assert_eq!(
+            list.registrable_domain("a.b.Example.co.uk").as_deref(),
+            Some("example.co.uk")
+        );
+        assert_eq!(list.registrable_domain("co.uk"), None);
+    }
+
+    #[test]
+    fn known_tlds() {
+        assert!(list().is_known_tld("Com"));
+        assert!(!list().is_known_tld("ywh"));
+
+        let embedded = SuffixList::load(None, None).unwrap();
+        assert!(embedded.is_known_tld("dev"));
+        assert!(!embedded.is_known_tld("ywh"));
+    }
+}
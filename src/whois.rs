@@ -0,0 +1,329 @@
+use crate::errors::{CheckError, ErrorKind};
+use crate::shared::{shared, Shared};
+use chrono::{NaiveDate, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{OnceCell, Semaphore};
+use tokio::time::timeout;
+
+// Root server that knows the WHOIS server of every TLD
+const IANA_SERVER: &str = "whois.iana.org";
+
+// Well-known servers, saves a round-trip to IANA for the most common TLDs
+const KNOWN_SERVERS: [(&str, &str); 12] = [
+    ("com", "whois.verisign-grs.com"),
+    ("net", "whois.verisign-grs.com"),
+    ("org", "whois.publicinterestregistry.org"),
+    ("info", "whois.nic.info"),
+    ("io", "whois.nic.io"),
+    ("co", "whois.registry.co"),
+    ("dev", "whois.nic.google"),
+    ("app", "whois.nic.google"),
+    ("me", "whois.nic.me"),
+    ("uk", "whois.nic.uk"),
+    ("de", "whois.denic.de"),
+    ("rs", "whois.rnids.rs"),
+];
+
+// Keys used by registries and registrars for the expiration date
+const EXPIRATION_KEYS: [&str; 14] = [
+    "registry expiry date",
+    "registrar registration expiration date",
+    "expiration date",
+    "expiry date",
+    "expire date",
+    "expires on",
+    "expiration time",
+    "expires",
+    "expire",
+    "paid-till",
+    "valid until",
+    "renewal date",
+    "free-date",
+    "domain expiration date",
+];
+
+// Keys pointing to another WHOIS server holding more details
+const REFERRAL_KEYS: [&str; 4] = [
+    "registrar whois server",
+    "whois server",
+    "referralserver",
+    "refer",
+];
+
+// Date layouts found in the wild, tried in order
+const DATE_FORMATS: [&str; 12] = [
+    "%Y-%m-%d", "%Y.%m.%d", "%Y/%m/%d", "%Y%m%d", "%d-%b-%Y", "%d.%m.%Y", "%d/%m/%Y", "%d-%m-%Y",
+    "%d %b %Y", "%b %d %Y", "%d %B %Y", "%B %d %Y",
+];
+
+// Maximum number of referrals to follow for a single lookup
+const MAX_REFERRALS: usize = 2;
+
+// Maximum size of a WHOIS response
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024;
+
+/// Result of a WHOIS lookup for a single domain
+pub struct WhoisResult {
+    pub expiration: Option<NaiveDate>,
+}
+
+impl WhoisResult {
+    /// Whether the registration has lapsed
+    pub fn is_expired(&self) -> bool {
+        self.expiration
+            .is_some_and(|date| date < Utc::now().date_naive())
+    }
+}
+
+/// WHOIS checker caching the server of each TLD and the expiration date of
+/// each registrable domain across every task, and holding the queries to each
+/// server to a few at a time as servers ban busy clients
+pub struct WhoisChecker {
+    timeout: Duration,
+    server_concurrency: usize,
+    servers: Mutex<HashMap<String, Shared<Option<String>>>>,
+    expirations: Mutex<HashMap<String, Shared<Option<NaiveDate>>>>,
+    slots: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl WhoisChecker {
+    pub fn new(timeout: Duration, server_concurrency: usize) -> Result<Self, String> {
+        if server_concurrency == 0 {
+            return Err("Invalid WHOIS Concurrency: 0".to_string());
+        }
+        let servers = KNOWN_SERVERS
+            .iter()
+            .map(|(tld, server)| {
+                let server = OnceCell::new_with(Some(Some(server.to_string())));
+                (tld.to_string(), Arc::new(server))
+            })
+            .collect();
+
+        Ok(WhoisChecker {
+            timeout,
+            server_concurrency,
+            servers: Mutex::new(servers),
+            expirations: Mutex::new(HashMap::new()),
+            slots: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Look up the expiration date of a domain, following referrals when the
+    /// registry does not provide one
+    pub async fn check_whois(
+        &self,
+        domain: &str,
+        verbose: bool,
+    ) -> Result<WhoisResult, CheckError> {
+        let expiration = shared(&self.expirations, domain);
+        let expiration = expiration
+            .get_or_try_init(|| self.lookup(domain, verbose))
+            .await?;
+        Ok(WhoisResult {
+            expiration: *expiration,
+        })
+    }
+
+    /// Query the registry of a domain, then the servers it refers to
+    async fn lookup(&self, domain: &str, verbose: bool) -> Result<Option<NaiveDate>, CheckError> {
+        let tld = domain.rsplit('.').next().unwrap_or(domain).to_lowercase();
+        let mut server = self.server_for(&tld).await?.ok_or_else(|| {
+            CheckError::new(ErrorKind::Other, format!("No WHOIS Server For: {}", tld))
+        })?;
+        let mut visited = vec![];
+
+        loop {
+            let response = self.query(&server, &query_for(&server, domain)).await?;
+            let expiration = find_value(&response, &EXPIRATION_KEYS).and_then(parse_date);
+            visited.push(server.clone());
+
+            if verbose {
+                println!(
+                    "WHOIS check for {} on {} found expiration date {:?}",
+                    domain, server, expiration
+                );
+            }
+
+            let referral = find_value(&response, &REFERRAL_KEYS)
+                .map(clean_server)
+                .filter(|referral| !referral.is_empty() && !visited.contains(referral));
+
+            match referral {
+                Some(referral) if expiration.is_none() && visited.len() <= MAX_REFERRALS => {
+                    server = referral;
+                }
+                _ => return Ok(expiration),
+            }
+        }
+    }
+
+    /// Find the WHOIS server of a TLD, asking IANA when it is not known yet
+    async fn server_for(&self, tld: &str) -> Result<Option<String>, CheckError> {
+        let server = shared(&self.servers, tld);
+        let server = server
+            .get_or_try_init(|| async {
+                let response = self.query(IANA_SERVER, tld).await?;
+                Ok::<_, CheckError>(
+                    find_value(&response, &["whois", "refer"])
+                        .map(clean_server)
+                        .filter(|server| !server.is_empty()),
+                )
+            })
+            .await?;
+        Ok(server.clone())
+    }
+
+    /// Send a query to a WHOIS server over port 43 and read the whole response,
+    /// once one of the slots of the server is free
+    async fn query(&self, server: &str, query: &str) -> Result<String, CheckError> {
+        let slots = self
+            .slots
+            .lock()
+            .unwrap()
+            .entry(server.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.server_concurrency)))
+            .clone();
+        let _permit = slots.acquire_owned().await;
+
+        let exchange = async {
+            let mut stream = TcpStream::connect((server, 43)).await?;
+            stream
+                .write_all(format!("{}\r\n", query).as_bytes())
+                .await?;
+
+            let mut response = vec![];
+            stream
+                .take(MAX_RESPONSE_BYTES)
+                .read_to_end(&mut response)
+                .await?;
+            Ok::<_, std::io::Error>(String::from_utf8_lossy(&response).into_owned())
+        };
+
+        timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| {
+                CheckError::new(
+                    ErrorKind::Timeout,
+                    format!("WHOIS Query Timed Out: {}", server),
+                )
+            })?
+            .map_err(|e| {
+                CheckError::new(
+                    ErrorKind::from_io(&e).unwrap_or(ErrorKind::Other),
+                    format!("WHOIS Query Failed: {}: {}", server, e),
+                )
+            })
+    }
+}
+
+/// Some servers expect extra flags around the domain
+fn query_for(server: &str, domain: &str) -> String {
+    match server {
+        "whois.denic.de" => format!("-T dn,ace {}", domain),
+        "whois.verisign-grs.com" => format!("domain {}", domain),
+        _ => domain.to_string(),
+    }
+}
+
+/// Find the value of the first `key: value` line matching one of the keys
+fn find_value<'a>(response: &'a str, keys: &[&str]) -> Option<&'a str> {
+    keys.iter().find_map(|key| {
+        response.lines().find_map(|line| {
+            let (name, value) = line.trim().split_once(':')?;
+            let value = value.trim();
+            (name.trim().eq_ignore_ascii_case(key) && !value.is_empty()).then_some(value)
+        })
+    })
+}
+
+/// Strip the scheme and port some registries put around server names
+fn clean_server(server: &str) -> String {
+    let server = server
+        .trim_start_matches("whois://")
+        .trim_start_matches("rwhois://");
+    server
+        .split([':', '/'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// Parse the many date layouts used by registries
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    // ISO-like timestamps, with or without a time part
+    let iso = value
+        .get(..10)
+        .and_then(|prefix| NaiveDate::parse_from_str(prefix, "%Y-%m-%d").ok());
+    if iso.is_some() {
+        return iso;
+    }
+
+    let value = value.replace(',', " ");
+    let tokens: Vec<&str> = value.split_whitespace().collect();
+
+    (1..=3).rev().find_map(|size| {
+        tokens.windows(size).find_map(|window| {
+            let candidate = window.join(" ");
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(&candidate, format).ok())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn parse_date_layouts() {
+        assert_eq!(parse_date("2030-01-15T04:00:00Z"), date(2030, 1, 15));
+        assert_eq!(parse_date("2030-01-15"), date(2030, 1, 15));
+        assert_eq!(parse_date("2030.01.15"), date(2030, 1, 15));
+        assert_eq!(parse_date("20300115"), date(2030, 1, 15));
+        assert_eq!(parse_date("15-Jan-2030"), date(2030, 1, 15));
+        assert_eq!(parse_date("15.01.2030 12:00:00"), date(2030, 1, 15));
+        assert_eq!(parse_date("Tue Jan 15 2030"), date(2030, 1, 15));
+        assert_eq!(parse_date("January 15, 2030"), date(2030, 1, 15));
+        assert_eq!(parse_date("not a date"), None);
+    }
+
+    #[test]
+    fn find_value_keys_in_order() {
+        let response = "Domain Name: EXAMPLE.COM\r\n\
+                        Registrar WHOIS Server: whois.registrar.example\r\n\
+                        Expiration Date:\r\n\
+                        Registry Expiry Date: 2030-01-15T04:00:00Z\r\n";
+
+        assert_eq!(
+            find_value(response, &EXPIRATION_KEYS),
+            Some("2030-01-15T04:00:00Z")
+        );
+        assert_eq!(
+            find_value(response, &REFERRAL_KEYS),
+            Some("whois.registrar.example")
+        );
+        assert_eq!(find_value(response, &["paid-till"]), None);
+    }
+
+    #[test]
+    fn clean_server_names() {
+        assert_eq!(
+            clean_server("whois://WHOIS.Example.net:43"),
+            "whois.example.net"
+        );
+        assert_eq!(
+            clean_server("rwhois://rwhois.example.net/"),
+            "rwhois.example.net"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use crate::cache::Cache;
+use crate::output::OutputFormat;
+use crate::report::{Record, ResultsFormat, StatusSource};
+use crate::status::Status;
+use crate::syntax::SubjectKind;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Arc;
+use tokio::sync::mpsc::Receiver;
+use tokio::task::{self, JoinHandle};
+
+// Number of outcomes between two flushes of the output and checkpoint files
+const FLUSH_INTERVAL: usize = 1000;
+
+// Bytes held across the output buffers before they are flushed early
+const FLUSH_BYTES: usize = 1 << 20;
+
+/// Result of a single check, sent by the workers to the writer task
+pub struct Outcome {
+    pub record: Record,
+    pub kind: Option<SubjectKind>,
+}
+
+/// Where and how results are written
+pub struct WriterSettings {
+    pub output_file: String,
+    pub output_formats: Vec<OutputFormat>,
+    pub exclude: String,
+    pub results_file: Option<String>,
+    pub results_format: ResultsFormat,
+    pub checkpoint_file: String,
+    pub cache: Option<Arc<Cache>>,
+}
+
+/// Spawn the task owning every output file, it flushes them all once the
+/// channel is closed
+pub fn spawn_writer(
+    settings: WriterSettings,
+    mut receiver: Receiver<Outcome>,
+) -> JoinHandle<Result<(), String>> {
+    task::spawn_blocking(move || {
+        let checkpoint = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&settings.checkpoint_file)
+            .map_err(|e| {
+                format!(
+                    "Checkpoint Open Failed: {}: {}",
+                    settings.checkpoint_file, e
+                )
+            })?;
+        let mut writer = Writer {
+            settings,
+            files: HashMap::new(),
+            buffered: 0,
+            checkpoint,
+            pending: String::new(),
+            cached: vec![],
+        };
+
+        let mut written = 0;
+        while let Some(outcome) = receiver.blocking_recv() {
+            writer.write(&outcome)?;
+
+            written += 1;
+            if written % FLUSH_INTERVAL == 0 || writer.buffered >= FLUSH_BYTES {
+                writer.flush()?;
+            }
+        }
+        writer.flush()
+    })
+}
+
+/// Output file opened on first use, with the lines not written yet
+struct OutputFile {
+    file: File,
+    buffer: String,
+}
+
+/// Buffered output files and the checkpoint listing the subjects already
+/// written. Nothing reaches the output files outside of a flush, so they are
+/// never ahead of the checkpoint by more than the flush in progress
+struct Writer {
+    settings: WriterSettings,
+    files: HashMap<String, OutputFile>,
+    /// Bytes held across every buffer
+    buffered: usize,
+    checkpoint: File,
+    pending: String,
+    /// Statuses waiting to be stored in the cache with the next flush
+    cached: Vec<(String, Status)>,
+}
+
+impl Writer {
+    /// Write an outcome to its status files, the results file and the cache
+    fn write(&mut self, outcome: &Outcome) -> Result<(), String> {
+        let record = &outcome.record;
+
+        if record.status.as_str() != self.settings.exclude {
+            for format in self.settings.output_formats.clone() {
+                let Some(line) = format.format_line(&record.subject, outcome.kind) else {
+                    continue;
+                };
+                let path = format.file_path(&self.settings.output_file, record.status);
+                self.write_line(&path, format.header(), &line)?;
+            }
+        }
+
+        if let Some(path) = self.settings.results_file.clone() {
+            let format = self.settings.results_format;
+            self.write_line(&path, format.header(), &record.to_line(format)?)?;
+        }
+
+        // Cached results keep their original timestamp so they expire on time
+        let fresh = record.source != StatusSource::Cache && record.status != Status::Invalid;
+        if self.settings.cache.is_some() && fresh {
+            self.cached.push((record.subject.clone(), record.status));
+        }
+
+        self.pending.push_str(&record.subject);
+        self.pending.push('\n');
+        Ok(())
+    }
+
+    /// Append a line to a file, writing the header first when the file is new
+    fn write_line(&mut self, path: &str, header: Option<String>, line: &str) -> Result<(), String> {
+        if !self.files.contains_key(path) {
+            let file = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .map_err(|e| format!("Output File Open Failed: {}: {}", path, e))?;
+            let is_new = file.metadata().map_err(|e| e.to_string())?.len() == 0;
+
+            let mut buffer = String::new();
+            if let Some(header) = header.filter(|_| is_new) {
+                buffer.push_str(&header);
+                buffer.push('\n');
+            }
+            self.buffered += buffer.len();
+            self.files
+                .insert(path.to_string(), OutputFile { file, buffer });
+        }
+
+        let output = self.files.get_mut(path).expect("file opened above");
+        output.buffer.push_str(line);
+        output.buffer.push('\n');
+        self.buffered += line.len() + 1;
+        Ok(())
+    }
+
+    /// Flush every buffered writer and the cache, the checkpoint last so it
+    /// never lists a subject whose results are not on disk yet
+    fn flush(&mut self) -> Result<(), String> {
+        for (path, output) in self.files.iter_mut() {
+            output
+                .file
+                .write_all(output.buffer.as_bytes())
+                .map_err(|e| format!("Output Write Failed: {}: {}", path, e))?;
+            output.buffer.clear();
+        }
+        self.buffered = 0;
+        if let Some(cache) = &self.settings.cache {
+            cache.put_all(&self.cached)?;
+        }
+        self.cached.clear();
+        self.checkpoint
+            .write_all(self.pending.as_bytes())
+            .map_err(|e| format!("Checkpoint Write Failed: {}", e))?;
+        self.pending.clear();
+        Ok(())
+    }
+}